@@ -63,6 +63,10 @@ RUST_CONF_OPENSSL_NO_OCSP
 RUST_CONF_OPENSSL_NO_PSK
 #endif
 
+#ifdef OPENSSL_NO_RC2
+RUST_CONF_OPENSSL_NO_RC2
+#endif
+
 #ifdef OPENSSL_NO_RFC3779
 RUST_CONF_OPENSSL_NO_RFC3779
 #endif
@@ -0,0 +1,38 @@
+use libc::*;
+use std::ptr;
+
+use *;
+
+cfg_if! {
+    if #[cfg(not(ossl300))] {
+        pub unsafe fn EVP_PKEY_CTX_set_dsa_paramgen_bits(ctx: *mut EVP_PKEY_CTX, nbits: c_int) -> c_int {
+            EVP_PKEY_CTX_ctrl(
+                ctx,
+                EVP_PKEY_DSA,
+                EVP_PKEY_OP_PARAMGEN,
+                EVP_PKEY_CTRL_DSA_PARAMGEN_BITS,
+                nbits,
+                ptr::null_mut(),
+            )
+        }
+
+        pub unsafe fn EVP_PKEY_CTX_set_dsa_paramgen_q_bits(ctx: *mut EVP_PKEY_CTX, qbits: c_int) -> c_int {
+            EVP_PKEY_CTX_ctrl(
+                ctx,
+                EVP_PKEY_DSA,
+                EVP_PKEY_OP_PARAMGEN,
+                EVP_PKEY_CTRL_DSA_PARAMGEN_Q_BITS,
+                qbits,
+                ptr::null_mut(),
+            )
+        }
+    }
+}
+
+pub const EVP_PKEY_CTRL_DSA_PARAMGEN_BITS: c_int = EVP_PKEY_ALG_CTRL + 1;
+pub const EVP_PKEY_CTRL_DSA_PARAMGEN_Q_BITS: c_int = EVP_PKEY_ALG_CTRL + 2;
+
+pub const DSA_FLAG_CACHE_MONT_P: c_int = 0x01;
+
+#[cfg(not(ossl110))]
+pub const DSA_FLAG_NO_EXP_CONSTTIME: c_int = 0x02;
@@ -6,6 +6,7 @@ pub const ERR_TXT_STRING: c_int = 0x02;
 pub const ERR_LIB_SYS: c_int = 2;
 pub const ERR_LIB_PEM: c_int = 9;
 pub const ERR_LIB_ASN1: c_int = 13;
+pub const ERR_LIB_USER: c_int = 128;
 
 cfg_if! {
     if #[cfg(ossl300)] {
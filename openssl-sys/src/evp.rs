@@ -2,6 +2,7 @@ use libc::*;
 use *;
 
 pub const EVP_MAX_MD_SIZE: c_uint = 64;
+pub const EVP_MAX_IV_LENGTH: c_int = 16;
 
 pub const PKCS5_SALT_LEN: c_int = 8;
 pub const PKCS12_DEFAULT_ITER: c_int = 2048;
@@ -23,9 +24,32 @@ pub const EVP_PKEY_CMAC: c_int = NID_cmac;
 #[cfg(ossl110)]
 pub const EVP_PKEY_HKDF: c_int = NID_hkdf;
 
+pub const EVP_CIPH_STREAM_CIPHER: c_ulong = 0x0;
+pub const EVP_CIPH_ECB_MODE: c_ulong = 0x1;
+pub const EVP_CIPH_CBC_MODE: c_ulong = 0x2;
+pub const EVP_CIPH_CFB_MODE: c_ulong = 0x3;
+pub const EVP_CIPH_OFB_MODE: c_ulong = 0x4;
+pub const EVP_CIPH_CTR_MODE: c_ulong = 0x5;
+pub const EVP_CIPH_GCM_MODE: c_ulong = 0x6;
+pub const EVP_CIPH_CCM_MODE: c_ulong = 0x7;
+pub const EVP_CIPH_XTS_MODE: c_ulong = 0x10001;
+pub const EVP_CIPH_WRAP_MODE: c_ulong = 0x10002;
+pub const EVP_CIPH_OCB_MODE: c_ulong = 0x10003;
+pub const EVP_CIPH_SIV_MODE: c_ulong = 0x10004;
+pub const EVP_CIPH_MODE: c_ulong = 0xF0007;
+pub const EVP_CIPH_VARIABLE_LENGTH: c_ulong = 0x8;
+pub const EVP_CIPH_CUSTOM_IV: c_ulong = 0x10;
+pub const EVP_CIPH_NO_PADDING: c_int = 0x100;
+pub const EVP_CIPHER_CTX_FLAG_WRAP_ALLOW: c_int = 0x1;
+pub const EVP_CIPH_FLAG_AEAD_CIPHER: c_ulong = 0x200000;
+
+pub const EVP_CTRL_GET_RC2_KEY_BITS: c_int = 0x2;
+pub const EVP_CTRL_SET_RC2_KEY_BITS: c_int = 0x3;
 pub const EVP_CTRL_GCM_SET_IVLEN: c_int = 0x9;
 pub const EVP_CTRL_GCM_GET_TAG: c_int = 0x10;
 pub const EVP_CTRL_GCM_SET_TAG: c_int = 0x11;
+pub const EVP_CTRL_GCM_SET_IV_FIXED: c_int = 0x12;
+pub const EVP_CTRL_GCM_IV_GEN: c_int = 0x13;
 
 pub unsafe fn EVP_get_digestbynid(type_: c_int) -> *const EVP_MD {
     EVP_get_digestbyname(OBJ_nid2sn(type_))
@@ -63,6 +87,16 @@ cfg_if! {
             EVP_CIPHER_get_nid(cipher)
         }
 
+        #[inline]
+        pub unsafe fn EVP_CIPHER_flags(cipher: *const EVP_CIPHER) -> c_ulong {
+            EVP_CIPHER_get_flags(cipher)
+        }
+
+        #[inline]
+        pub unsafe fn EVP_CIPHER_name(cipher: *const EVP_CIPHER) -> *const c_char {
+            EVP_CIPHER_get0_name(cipher)
+        }
+
         #[inline]
         pub unsafe fn EVP_CIPHER_CTX_block_size(ctx: *const EVP_CIPHER_CTX) -> c_int {
             EVP_CIPHER_CTX_get_block_size(ctx)
@@ -77,6 +111,21 @@ cfg_if! {
         pub unsafe fn EVP_CIPHER_CTX_iv_length(ctx: *const EVP_CIPHER_CTX) -> c_int {
             EVP_CIPHER_CTX_get_iv_length(ctx)
         }
+
+        #[inline]
+        pub unsafe fn EVP_CIPHER_CTX_encrypting(ctx: *const EVP_CIPHER_CTX) -> c_int {
+            EVP_CIPHER_CTX_is_encrypting(ctx)
+        }
+
+        #[inline]
+        pub unsafe fn EVP_CIPHER_CTX_num(ctx: *const EVP_CIPHER_CTX) -> c_int {
+            EVP_CIPHER_CTX_get_num(ctx)
+        }
+    } else {
+        #[inline]
+        pub unsafe fn EVP_CIPHER_name(cipher: *const EVP_CIPHER) -> *const c_char {
+            OBJ_nid2sn(EVP_CIPHER_nid(cipher))
+        }
     }
 }
 #[cfg(not(ossl300))]
@@ -117,6 +166,7 @@ cfg_if! {
     }
 }
 
+pub const EVP_PKEY_OP_PARAMGEN: c_int = 1 << 1;
 pub const EVP_PKEY_OP_KEYGEN: c_int = 1 << 2;
 cfg_if! {
     if #[cfg(ossl300)] {
@@ -93,6 +93,18 @@ extern "C" {
         m: *const BIGNUM,
         ctx: *mut BN_CTX,
     ) -> c_int;
+    pub fn BN_mod_exp_mont(
+        r: *mut BIGNUM,
+        a: *const BIGNUM,
+        p: *const BIGNUM,
+        m: *const BIGNUM,
+        ctx: *mut BN_CTX,
+        m_ctx: *mut BN_MONT_CTX,
+    ) -> c_int;
+
+    pub fn BN_MONT_CTX_new() -> *mut BN_MONT_CTX;
+    pub fn BN_MONT_CTX_free(mont: *mut BN_MONT_CTX);
+    pub fn BN_MONT_CTX_set(mont: *mut BN_MONT_CTX, mod_: *const BIGNUM, ctx: *mut BN_CTX) -> c_int;
 
     pub fn BN_mask_bits(a: *mut BIGNUM, n: c_int) -> c_int;
     pub fn BN_rshift(r: *mut BIGNUM, a: *const BIGNUM, n: c_int) -> c_int;
@@ -135,6 +147,19 @@ extern "C" {
         do_trial_division: c_int,
         cb: *mut BN_GENCB,
     ) -> c_int;
+
+    #[cfg(ossl110)]
+    pub fn BN_GENCB_new() -> *mut BN_GENCB;
+    #[cfg(ossl110)]
+    pub fn BN_GENCB_free(cb: *mut BN_GENCB);
+    #[cfg(ossl110)]
+    pub fn BN_GENCB_set(
+        gencb: *mut BN_GENCB,
+        callback: Option<unsafe extern "C" fn(c_int, c_int, *mut BN_GENCB) -> c_int>,
+        cb_arg: *mut c_void,
+    );
+    #[cfg(ossl110)]
+    pub fn BN_GENCB_get_arg(cb: *mut BN_GENCB) -> *mut c_void;
 }
 
 cfg_if! {
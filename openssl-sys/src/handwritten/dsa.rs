@@ -2,11 +2,24 @@ use libc::*;
 
 use *;
 
+cfg_if! {
+    if #[cfg(ossl300)] {
+        extern "C" {
+            pub fn EVP_PKEY_CTX_set_dsa_paramgen_bits(ctx: *mut EVP_PKEY_CTX, nbits: c_int) -> c_int;
+            pub fn EVP_PKEY_CTX_set_dsa_paramgen_q_bits(ctx: *mut EVP_PKEY_CTX, qbits: c_int) -> c_int;
+        }
+    }
+}
+
 extern "C" {
     pub fn DSA_new() -> *mut DSA;
     pub fn DSA_free(dsa: *mut DSA);
     pub fn DSA_up_ref(dsa: *mut DSA) -> c_int;
     pub fn DSA_size(dsa: *const DSA) -> c_int;
+    #[cfg(ossl110)]
+    pub fn DSA_security_bits(dsa: *const DSA) -> c_int;
+    #[cfg(ossl110)]
+    pub fn DSA_bits(dsa: *const DSA) -> c_int;
     pub fn DSA_sign(
         dummy: c_int,
         dgst: *const c_uchar,
@@ -24,9 +37,31 @@ extern "C" {
         dsa: *mut DSA,
     ) -> c_int;
 
+    pub fn DSA_SIG_new() -> *mut DSA_SIG;
+    pub fn DSA_SIG_free(sig: *mut DSA_SIG);
+    #[cfg(any(ossl110, libressl273))]
+    pub fn DSA_SIG_get0(sig: *const DSA_SIG, pr: *mut *const BIGNUM, ps: *mut *const BIGNUM);
+    #[cfg(any(ossl110, libressl273))]
+    pub fn DSA_SIG_set0(sig: *mut DSA_SIG, pr: *mut BIGNUM, ps: *mut BIGNUM) -> c_int;
+    pub fn DSA_do_sign(dgst: *const c_uchar, dlen: c_int, dsa: *mut DSA) -> *mut DSA_SIG;
+    pub fn DSA_do_verify(
+        dgst: *const c_uchar,
+        dgst_len: c_int,
+        sig: *mut DSA_SIG,
+        dsa: *mut DSA,
+    ) -> c_int;
+
+    pub fn d2i_DSA_SIG(
+        sig: *mut *mut DSA_SIG,
+        inp: *mut *const c_uchar,
+        length: c_long,
+    ) -> *mut DSA_SIG;
+    pub fn i2d_DSA_SIG(sig: *const DSA_SIG, out: *mut *mut c_uchar) -> c_int;
+
     pub fn d2i_DSAPublicKey(a: *mut *mut DSA, pp: *mut *const c_uchar, length: c_long) -> *mut DSA;
     pub fn d2i_DSAPrivateKey(a: *mut *mut DSA, pp: *mut *const c_uchar, length: c_long)
         -> *mut DSA;
+    pub fn d2i_DSAparams(a: *mut *mut DSA, pp: *mut *const c_uchar, length: c_long) -> *mut DSA;
 
     pub fn DSA_generate_parameters_ex(
         dsa: *mut DSA,
@@ -41,6 +76,9 @@ extern "C" {
     pub fn DSA_generate_key(dsa: *mut DSA) -> c_int;
     pub fn i2d_DSAPublicKey(a: *const DSA, pp: *mut *mut c_uchar) -> c_int;
     pub fn i2d_DSAPrivateKey(a: *const DSA, pp: *mut *mut c_uchar) -> c_int;
+    pub fn i2d_DSAparams(a: *const DSA, pp: *mut *mut c_uchar) -> c_int;
+
+    pub fn DSA_print(bp: *mut BIO, x: *const DSA, off: c_int) -> c_int;
 
     #[cfg(any(ossl110, libressl273))]
     pub fn DSA_get0_pqg(
@@ -55,4 +93,10 @@ extern "C" {
     pub fn DSA_get0_key(d: *const DSA, pub_key: *mut *const BIGNUM, priv_key: *mut *const BIGNUM);
     #[cfg(any(ossl110, libressl273))]
     pub fn DSA_set0_key(d: *mut DSA, pub_key: *mut BIGNUM, priv_key: *mut BIGNUM) -> c_int;
+    #[cfg(any(ossl110, libressl273))]
+    pub fn DSA_test_flags(d: *const DSA, flags: c_int) -> c_int;
+    #[cfg(any(ossl110, libressl273))]
+    pub fn DSA_set_flags(d: *mut DSA, flags: c_int);
+    #[cfg(any(ossl110, libressl273))]
+    pub fn DSA_clear_flags(d: *mut DSA, flags: c_int);
 }
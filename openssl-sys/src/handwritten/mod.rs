@@ -14,9 +14,11 @@ pub use handwritten::hmac::*;
 pub use handwritten::kdf::*;
 pub use handwritten::object::*;
 pub use handwritten::ocsp::*;
+pub use handwritten::params::*;
 pub use handwritten::pem::*;
 pub use handwritten::pkcs12::*;
 pub use handwritten::pkcs7::*;
+pub use handwritten::provider::*;
 pub use handwritten::rand::*;
 pub use handwritten::rsa::*;
 pub use handwritten::safestack::*;
@@ -46,9 +48,11 @@ mod hmac;
 mod kdf;
 mod object;
 mod ocsp;
+mod params;
 mod pem;
 mod pkcs12;
 mod pkcs7;
+mod provider;
 mod rand;
 mod rsa;
 mod safestack;
@@ -0,0 +1,50 @@
+use libc::*;
+use *;
+
+extern "C" {
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_free(params: *mut OSSL_PARAM);
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_construct_size_t(key: *const c_char, buf: *mut size_t) -> OSSL_PARAM;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_construct_end() -> OSSL_PARAM;
+
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_new() -> *mut OSSL_PARAM_BLD;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_free(bld: *mut OSSL_PARAM_BLD);
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_to_param(bld: *mut OSSL_PARAM_BLD) -> *mut OSSL_PARAM;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_push_int(
+        bld: *mut OSSL_PARAM_BLD,
+        key: *const c_char,
+        val: c_int,
+    ) -> c_int;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_push_uint(
+        bld: *mut OSSL_PARAM_BLD,
+        key: *const c_char,
+        val: c_uint,
+    ) -> c_int;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_push_size_t(
+        bld: *mut OSSL_PARAM_BLD,
+        key: *const c_char,
+        val: size_t,
+    ) -> c_int;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_push_utf8_string(
+        bld: *mut OSSL_PARAM_BLD,
+        key: *const c_char,
+        buf: *const c_char,
+        bsize: size_t,
+    ) -> c_int;
+    #[cfg(ossl300)]
+    pub fn OSSL_PARAM_BLD_push_octet_string(
+        bld: *mut OSSL_PARAM_BLD,
+        key: *const c_char,
+        buf: *const c_void,
+        bsize: size_t,
+    ) -> c_int;
+}
@@ -127,6 +127,13 @@ extern "C" {
         callback: pem_password_cb,
         user_data: *mut c_void,
     ) -> *mut DSA;
+    pub fn PEM_read_bio_DSAparams(
+        bp: *mut BIO,
+        dsa: *mut *mut DSA,
+        callback: pem_password_cb,
+        user_data: *mut c_void,
+    ) -> *mut DSA;
+    pub fn PEM_write_bio_DSAparams(bp: *mut BIO, dsa: *const DSA) -> c_int;
     pub fn PEM_read_bio_ECPrivateKey(
         bio: *mut BIO,
         key: *mut *mut EC_KEY,
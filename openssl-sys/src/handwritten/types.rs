@@ -186,6 +186,18 @@ cfg_if! {
 }
 pub enum DSA_METHOD {}
 
+cfg_if! {
+    if #[cfg(any(ossl110, libressl280))] {
+        pub enum DSA_SIG {}
+    } else {
+        #[repr(C)]
+        pub struct DSA_SIG {
+            pub r: *mut BIGNUM,
+            pub s: *mut BIGNUM,
+        }
+    }
+}
+
 cfg_if! {
     if #[cfg(any(ossl110, libressl280))] {
         pub enum RSA {}
@@ -1073,3 +1085,19 @@ pub enum OCSP_RESPONSE {}
 
 #[cfg(ossl300)]
 pub enum OSSL_LIB_CTX {}
+
+#[cfg(ossl300)]
+pub enum OSSL_PROVIDER {}
+
+#[cfg(ossl300)]
+#[repr(C)]
+pub struct OSSL_PARAM {
+    pub key: *const c_char,
+    pub data_type: c_uint,
+    pub data: *mut c_void,
+    pub data_size: size_t,
+    pub return_size: size_t,
+}
+
+#[cfg(ossl300)]
+pub enum OSSL_PARAM_BLD {}
@@ -201,6 +201,11 @@ extern "C" {
     pub fn X509_to_X509_REQ(x: *mut X509, pkey: *mut EVP_PKEY, md: *const EVP_MD) -> *mut X509_REQ;
 
     pub fn X509_ALGOR_free(x: *mut X509_ALGOR);
+    pub fn d2i_X509_ALGOR(
+        a: *mut *mut X509_ALGOR,
+        pp: *mut *const c_uchar,
+        length: c_long,
+    ) -> *mut X509_ALGOR;
 
     pub fn X509_REVOKED_new() -> *mut X509_REVOKED;
     pub fn X509_REVOKED_free(x: *mut X509_REVOKED);
@@ -25,6 +25,7 @@ pub use bio::*;
 pub use bn::*;
 pub use cms::*;
 pub use crypto::*;
+pub use dsa::*;
 pub use dtls1::*;
 pub use ec::*;
 pub use err::*;
@@ -55,6 +56,7 @@ mod bio;
 mod bn;
 mod cms;
 mod crypto;
+mod dsa;
 mod dtls1;
 mod ec;
 mod err;
@@ -912,6 +912,8 @@ pub const NID_rc4_hmac_md5: c_int = 915;
 pub const NID_aes_128_cbc_hmac_sha1: c_int = 916;
 pub const NID_aes_192_cbc_hmac_sha1: c_int = 917;
 pub const NID_aes_256_cbc_hmac_sha1: c_int = 918;
+#[cfg(ossl110)]
+pub const NID_chacha20_poly1305: c_int = 1018;
 #[cfg(ossl111)]
 pub const NID_X25519: c_int = 1034;
 #[cfg(ossl111)]
@@ -0,0 +1,49 @@
+//! Compares encrypting many messages into a freshly allocated buffer each time
+//! with reusing one buffer through `cipher_update_reuse`.
+//!
+//! Run with `cargo bench --bench cipher_update_reuse`.
+
+use std::time::Instant;
+
+use openssl::cipher::Cipher;
+use openssl::cipher_ctx::CipherCtx;
+
+const MESSAGES: usize = 100_000;
+const MESSAGE_LEN: usize = 1024;
+
+fn run<F>(name: &str, mut f: F)
+where
+    F: FnMut(&mut CipherCtx, &[u8]),
+{
+    let mut ctx = CipherCtx::new().unwrap();
+    ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&[0; 16]), Some(&[0; 16]))
+        .unwrap();
+    let message = vec![0x55; MESSAGE_LEN];
+
+    let start = Instant::now();
+    for _ in 0..MESSAGES {
+        f(&mut ctx, &message);
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<10} {:>10.0} messages/s ({:?} per message)",
+        name,
+        MESSAGES as f64 / elapsed.as_secs_f64(),
+        elapsed / MESSAGES as u32,
+    );
+}
+
+fn main() {
+    run("fresh", |ctx, message| {
+        let mut out = vec![];
+        ctx.cipher_update_vec(message, &mut out).unwrap();
+        assert_eq!(out.len(), MESSAGE_LEN);
+    });
+
+    let mut out = Vec::new();
+    run("reuse", |ctx, message| {
+        ctx.cipher_update_reuse(message, &mut out).unwrap();
+        assert_eq!(out.len(), MESSAGE_LEN);
+    });
+}
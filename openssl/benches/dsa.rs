@@ -0,0 +1,50 @@
+//! Measures DSA signing and verification throughput through `Signer` and
+//! `Verifier`, the same code path used by applications.
+//!
+//! Run with `cargo bench --bench dsa`.
+
+use std::time::{Duration, Instant};
+
+use openssl::dsa::Dsa;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::{Signer, Verifier};
+
+const ITERATIONS: u32 = 200;
+
+fn per_second(time: Duration) -> f64 {
+    f64::from(ITERATIONS) / time.as_secs_f64()
+}
+
+fn main() {
+    let message = [0x5a; 1024];
+
+    for &bits in &[1024, 2048, 3072] {
+        // key generation is not included in the measurements
+        let pkey = PKey::from_dsa(Dsa::generate(bits).unwrap()).unwrap();
+
+        let start = Instant::now();
+        let mut signature = vec![];
+        for _ in 0..ITERATIONS {
+            let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+            signer.update(&message).unwrap();
+            signature = signer.sign_to_vec().unwrap();
+        }
+        let sign_time = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+            verifier.update(&message).unwrap();
+            assert!(verifier.verify(&signature).unwrap());
+        }
+        let verify_time = start.elapsed();
+
+        println!(
+            "{:>4} bit: {:>8.0} signatures/s {:>8.0} verifications/s",
+            bits,
+            per_second(sign_time),
+            per_second(verify_time),
+        );
+    }
+}
@@ -50,16 +50,39 @@
 //! ```
 #![warn(missing_docs)]
 
+#[cfg(ossl300)]
+use crate::cipher::Cipher;
 use crate::cipher::CipherRef;
+#[cfg(ossl300)]
+use crate::cvt_n;
 use crate::error::ErrorStack;
+use crate::hash::{Hasher, MessageDigest};
+use crate::memcmp;
+#[cfg(ossl300)]
+use crate::nid::Nid;
+#[cfg(ossl300)]
+use crate::ossl_param::OsslParamArray;
 use crate::pkey::{HasPrivate, HasPublic, PKey, PKeyRef};
+use crate::rand::rand_bytes;
+use crate::sign::Signer;
 use crate::{cvt, cvt_p};
+use bitflags::bitflags;
 use cfg_if::cfg_if;
 use foreign_types::{ForeignType, ForeignTypeRef};
-use libc::{c_int, c_uchar};
+#[cfg(ossl300)]
+use libc::{c_char, size_t};
+use libc::{c_int, c_uchar, c_void};
 use openssl_macros::corresponds;
+use std::cmp;
+use std::collections::{HashSet, VecDeque};
 use std::convert::TryFrom;
+use std::error;
+use std::ffi::CStr;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
 use std::ptr;
+use std::sync::{Arc, Mutex, PoisonError};
 
 cfg_if! {
     if #[cfg(ossl300)] {
@@ -69,6 +92,44 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(ossl110)] {
+        use ffi::EVP_CIPHER_CTX_reset;
+    } else {
+        use ffi::EVP_CIPHER_CTX_cleanup as EVP_CIPHER_CTX_reset;
+    }
+}
+
+bitflags! {
+    /// Flags controlling the behavior of a [`CipherCtx`].
+    pub struct CipherCtxFlags: c_int {
+        /// Allows the context to be initialized with key wrap ciphers such as [`Cipher::aes_128_wrap`], which
+        /// OpenSSL versions before 3.0.0 otherwise refuse. This must be set before the cipher is set.
+        ///
+        /// [`Cipher::aes_128_wrap`]: crate::cipher::Cipher::aes_128_wrap
+        const WRAP_ALLOW = ffi::EVP_CIPHER_CTX_FLAG_WRAP_ALLOW;
+        /// Disables padding, like [`CipherCtxRef::set_padding`]. This can be changed at any time before the
+        /// operation is finalized.
+        const NO_PADDING = ffi::EVP_CIPH_NO_PADDING;
+    }
+}
+
+// The length of the authentication tag appended by the one-shot helpers.
+const ONE_SHOT_TAG_LEN: usize = 16;
+
+// The block length of XTS mode, which is also the length of its tweak.
+const XTS_BLOCK_LEN: usize = 16;
+
+// The leading fields of the context, which is not opaque in older versions.
+#[cfg(not(any(ossl110, libressl273)))]
+#[allow(dead_code)]
+#[repr(C)]
+struct CipherCtxPrefix {
+    cipher: *const ffi::EVP_CIPHER,
+    engine: *mut ffi::ENGINE,
+    encrypt: c_int,
+}
+
 foreign_type_and_impl_send_sync! {
     type CType = ffi::EVP_CIPHER_CTX;
     fn drop = ffi::EVP_CIPHER_CTX_free;
@@ -90,6 +151,334 @@ impl CipherCtx {
             Ok(CipherCtx::from_ptr(ptr))
         }
     }
+
+    /// Creates a context which resumes the operation recorded by [`CipherCtxRef::checkpoint`].
+    ///
+    /// `key` must be the key the original context was initialized with, as it is not part of the checkpoint.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher.
+    #[corresponds(EVP_CIPHER_CTX_set_num)]
+    #[cfg(ossl300)]
+    pub fn restore(checkpoint: &CipherCheckpoint, key: &[u8]) -> Result<Self, ErrorStack> {
+        let cipher = Cipher::from_nid(checkpoint.nid)
+            .ok_or_else(|| ErrorStack::internal_error("unknown checkpoint cipher"))?;
+        let mode = unsafe { ffi::EVP_CIPHER_flags(cipher.as_ptr()) } & ffi::EVP_CIPH_MODE;
+
+        let mut ctx = CipherCtx::new()?;
+        let init = |ctx: &mut CipherCtxRef, iv: &[u8]| {
+            if checkpoint.encrypting {
+                ctx.encrypt_init(Some(cipher), Some(key), Some(iv))
+            } else {
+                ctx.decrypt_init(Some(cipher), Some(key), Some(iv))
+            }
+        };
+
+        if mode == ffi::EVP_CIPH_CTR_MODE && checkpoint.num != 0 {
+            // The partially used keystream block is not part of the state which can be set, so it is regenerated
+            // from the previous counter value instead.
+            let mut iv = checkpoint.iv.clone();
+            decrement_be(&mut iv);
+            init(&mut ctx, &iv)?;
+
+            let zeros = vec![0; checkpoint.num as usize];
+            let mut discard = vec![0; zeros.len()];
+            ctx.cipher_update(&zeros, Some(&mut discard))?;
+        } else {
+            init(&mut ctx, &checkpoint.iv)?;
+            unsafe {
+                cvt(ffi::EVP_CIPHER_CTX_set_num(
+                    ctx.as_ptr(),
+                    checkpoint.num as c_int,
+                ))?;
+            }
+        }
+
+        Ok(ctx)
+    }
+
+    /// Encrypts `data` in one step, returning the ciphertext.
+    ///
+    /// A context is initialized with `cipher`, `key`, and `iv`, and all of `data` is processed with padding enabled.
+    /// For authenticated ciphers such as AES-GCM, no additional data is authenticated and the 16 byte tag is appended
+    /// to the ciphertext.
+    ///
+    /// Unlike [`CipherCtxRef::encrypt_init`], this returns an error rather than panicking if `key` is shorter than the
+    /// key length of the cipher, or if `iv` is shorter than its IV length. For authenticated ciphers, the IV length is
+    /// adjusted to the length of `iv` instead.
+    pub fn encrypt(
+        cipher: &CipherRef,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        data: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        if cipher_is_aead(cipher) {
+            let mut tag = [0; ONE_SHOT_TAG_LEN];
+            let mut out = CipherCtx::encrypt_aead(cipher, key, iv, &[], data, &mut tag)?;
+            out.extend_from_slice(&tag);
+            return Ok(out);
+        }
+
+        let mut ctx = CipherCtx::one_shot(cipher, key, iv, true, |_| Ok(()))?;
+        let mut out = vec![];
+        ctx.cipher_update_vec(data, &mut out)?;
+        ctx.cipher_final_vec(&mut out)?;
+        Ok(out)
+    }
+
+    /// Decrypts `data` in one step, returning the plaintext.
+    ///
+    /// This is the inverse of [`CipherCtx::encrypt`]. For authenticated ciphers, `data` must end with the 16 byte
+    /// tag, and an error is returned if it does not verify.
+    pub fn decrypt(
+        cipher: &CipherRef,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        data: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        if cipher_is_aead(cipher) {
+            if data.len() < ONE_SHOT_TAG_LEN {
+                return Err(ErrorStack::internal_error(
+                    "ciphertext is shorter than the authentication tag",
+                ));
+            }
+            let (data, tag) = data.split_at(data.len() - ONE_SHOT_TAG_LEN);
+            return CipherCtx::decrypt_aead(cipher, key, iv, &[], data, tag);
+        }
+
+        let mut ctx = CipherCtx::one_shot(cipher, key, iv, false, |_| Ok(()))?;
+        let mut out = vec![];
+        ctx.cipher_update_vec(data, &mut out)?;
+        ctx.cipher_final_vec(&mut out)?;
+        Ok(out)
+    }
+
+    /// Encrypts `plaintext` with an authenticated cipher in one step, returning the ciphertext.
+    ///
+    /// `aad` is authenticated as additional data, and the authentication tag is written to `tag`. The length of
+    /// `tag` determines the length of the generated tag, which for AES-GCM should be the maximum of 16 bytes.
+    ///
+    /// Returns an error if `cipher` is not an authenticated cipher, as well as under the conditions described for
+    /// [`CipherCtx::encrypt`].
+    pub fn encrypt_aead(
+        cipher: &CipherRef,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        aad: &[u8],
+        plaintext: &[u8],
+        tag: &mut [u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        if !cipher_is_aead(cipher) {
+            return Err(ErrorStack::internal_error(
+                "cipher is not an authenticated cipher",
+            ));
+        }
+
+        let tag_len = tag.len();
+        let mut ctx = CipherCtx::one_shot(cipher, key, iv, true, |ctx| match ctx.cipher_mode() {
+            CipherMode::Ccm | CipherMode::Ocb => ctx.set_tag_length(tag_len),
+            _ => Ok(()),
+        })?;
+        if ctx.cipher_mode() == CipherMode::Ccm {
+            ctx.set_data_len(plaintext.len())?;
+        }
+
+        ctx.set_aad(aad)?;
+        let mut out = vec![];
+        ctx.cipher_update_vec(plaintext, &mut out)?;
+        ctx.cipher_final_vec(&mut out)?;
+        ctx.tag(tag)?;
+        Ok(out)
+    }
+
+    /// Decrypts `ciphertext` with an authenticated cipher in one step, returning the plaintext.
+    ///
+    /// `aad` is the additional data and `tag` the authentication tag produced by [`CipherCtx::encrypt_aead`]. The
+    /// tag is verified before any plaintext is returned, and an error is returned if it does not match, for example
+    /// because the ciphertext or additional data has been tampered with.
+    pub fn decrypt_aead(
+        cipher: &CipherRef,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        if !cipher_is_aead(cipher) {
+            return Err(ErrorStack::internal_error(
+                "cipher is not an authenticated cipher",
+            ));
+        }
+
+        let mut ctx = CipherCtx::one_shot(cipher, key, iv, false, |ctx| match ctx.cipher_mode() {
+            CipherMode::Ccm | CipherMode::Ocb => ctx.set_tag(tag),
+            _ => Ok(()),
+        })?;
+        let ccm = ctx.cipher_mode() == CipherMode::Ccm;
+        if ccm {
+            ctx.set_data_len(ciphertext.len())?;
+        }
+
+        ctx.set_aad(aad)?;
+        let mut out = vec![];
+        ctx.cipher_update_vec(ciphertext, &mut out)?;
+        // CCM verifies the tag as part of the update.
+        if !ccm {
+            ctx.set_tag(tag)?;
+            ctx.cipher_final_vec(&mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Encrypts `plaintext` with a CCM mode cipher such as AES-128-CCM in one step, returning the ciphertext and a
+    /// tag of `tag_len` bytes.
+    ///
+    /// CCM needs the nonce and tag lengths before the key, and the length of the plaintext before the additional
+    /// data, so the whole message must be available up front. This performs those steps in the required order.
+    /// `nonce` must be 7 to 13 bytes long, and `tag_len` an even number from 4 to 16.
+    pub fn encrypt_ccm(
+        cipher: &CipherRef,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+        tag_len: usize,
+    ) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+        check_ccm(cipher, nonce)?;
+
+        let mut tag = vec![0; tag_len];
+        let ciphertext =
+            CipherCtx::encrypt_aead(cipher, key, Some(nonce), aad, plaintext, &mut tag)?;
+        Ok((ciphertext, tag))
+    }
+
+    /// Decrypts `ciphertext` with a CCM mode cipher in one step, returning the plaintext.
+    ///
+    /// This is the inverse of [`CipherCtx::encrypt_ccm`]. CCM verifies the tag while decrypting, so it is set before
+    /// the ciphertext is processed in a single step. An error is returned if it does not match.
+    pub fn decrypt_ccm(
+        cipher: &CipherRef,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        check_ccm(cipher, nonce)?;
+
+        CipherCtx::decrypt_aead(cipher, key, Some(nonce), aad, ciphertext, tag)
+    }
+
+    /// Wraps `key` under the key-encryption key `kek` with an AES key wrap cipher.
+    ///
+    /// `cipher` must be a key wrap cipher, either [`Cipher::aes_128_wrap`] or one of its variants for RFC 3394 key
+    /// wrap, which requires `key` to be a multiple of 8 bytes and at least 16 bytes long, or
+    /// [`Cipher::aes_128_wrap_pad`] or one of its variants for RFC 5649 key wrap with padding, which accepts keys of
+    /// any length. If `iv` is `None`, the default initial value of the algorithm is used, which is
+    /// `A6A6A6A6A6A6A6A6` for RFC 3394 and `A65959A6` for RFC 5649.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    ///
+    /// [`Cipher::aes_128_wrap`]: crate::cipher::Cipher::aes_128_wrap
+    /// [`Cipher::aes_128_wrap_pad`]: crate::cipher::Cipher::aes_128_wrap_pad
+    #[cfg(ossl110)]
+    pub fn wrap_key(
+        cipher: &CipherRef,
+        kek: &[u8],
+        iv: Option<&[u8]>,
+        key: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        CipherCtx::key_wrap(cipher, kek, iv, key, true)
+    }
+
+    /// Unwraps a key wrapped by [`CipherCtx::wrap_key`].
+    ///
+    /// `cipher`, `kek` and `iv` must match those used to wrap the key. An error is returned if the integrity check of
+    /// the key wrap algorithm fails, for example because the wrapped key was modified.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[cfg(ossl110)]
+    pub fn unwrap_key(
+        cipher: &CipherRef,
+        kek: &[u8],
+        iv: Option<&[u8]>,
+        wrapped: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        CipherCtx::key_wrap(cipher, kek, iv, wrapped, false)
+    }
+
+    #[cfg(ossl110)]
+    fn key_wrap(
+        cipher: &CipherRef,
+        kek: &[u8],
+        iv: Option<&[u8]>,
+        input: &[u8],
+        encrypting: bool,
+    ) -> Result<Vec<u8>, ErrorStack> {
+        let mut ctx = CipherCtx::one_shot(cipher, kek, iv, encrypting, |_| Ok(()))?;
+        if ctx.cipher_mode() != CipherMode::Wrap {
+            return Err(ErrorStack::internal_error(
+                "cipher is not a key wrap cipher",
+            ));
+        }
+
+        let mut out = vec![];
+        ctx.cipher_update_vec(input, &mut out)?;
+        ctx.cipher_final_vec(&mut out)?;
+        Ok(out)
+    }
+
+    // Creates a context for a one-shot operation, reporting unusable key and IV lengths as errors. `configure` is
+    // called after the cipher is set but before the key is, as is required to set the tag of CCM mode ciphers.
+    fn one_shot<F>(
+        cipher: &CipherRef,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        encrypting: bool,
+        configure: F,
+    ) -> Result<CipherCtx, ErrorStack>
+    where
+        F: FnOnce(&mut CipherCtxRef) -> Result<(), ErrorStack>,
+    {
+        if key.len() < cipher.key_length() {
+            return Err(ErrorStack::internal_error(
+                "key is shorter than the key length of the cipher",
+            ));
+        }
+
+        let mut ctx = CipherCtx::new()?;
+        // Output buffers are sized for key wrap ciphers, so they can be used safely.
+        ctx.set_flags(CipherCtxFlags::WRAP_ALLOW);
+        let init = |ctx: &mut CipherCtxRef, type_, key, iv| {
+            if encrypting {
+                ctx.encrypt_init(type_, key, iv)
+            } else {
+                ctx.decrypt_init(type_, key, iv)
+            }
+        };
+
+        init(&mut ctx, Some(cipher), None, None)?;
+        if let Some(iv) = iv {
+            if ctx.is_aead() {
+                // The context's IV length is compared, as it can differ from the cipher's; CCM reports a 12 byte IV
+                // for the cipher but defaults to a 7 byte nonce.
+                if iv.len() != ctx.iv_length() {
+                    ctx.set_iv_length(iv.len())?;
+                }
+            } else if iv.len() < cipher.iv_length() {
+                return Err(ErrorStack::internal_error(
+                    "IV is shorter than the IV length of the cipher",
+                ));
+            }
+        }
+        configure(&mut ctx)?;
+        init(&mut ctx, None, Some(key), iv)?;
+
+        Ok(ctx)
+    }
 }
 
 impl CipherCtxRef {
@@ -133,6 +522,162 @@ impl CipherCtxRef {
         self.cipher_init(type_, key, iv, ffi::EVP_DecryptInit_ex)
     }
 
+    /// Initializes the context for encryption, checking the nonce against `tracker`.
+    ///
+    /// This is [`Self::encrypt_init`] with both the key and the IV set. If `type_` is `None`, the cipher the context
+    /// has already been initialized with is used. For AEAD ciphers, an error is returned without touching the context
+    /// if the key has already been used with the IV within the tracker's window, and the pair is recorded once the
+    /// context has been initialized. Other ciphers are initialized without consulting the tracker.
+    ///
+    /// [`Self::encrypt_init`] never consults a tracker. To have every initialization of a context checked, use a
+    /// [`CipherSession`] with [`CipherSession::set_nonce_tracker`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher, the IV buffer is smaller than the IV size
+    /// of the cipher, or if `type_` is `None` and the context has not been initialized with a cipher.
+    #[corresponds(EVP_EncryptInit_ex)]
+    pub fn encrypt_init_checked(
+        &mut self,
+        type_: Option<&CipherRef>,
+        key: &[u8],
+        iv: &[u8],
+        tracker: &Mutex<NonceTracker>,
+    ) -> Result<(), ErrorStack> {
+        let (cipher, iv_len) = match type_ {
+            Some(cipher) => (cipher.as_ptr() as *const _, cipher.iv_length()),
+            None => unsafe { (EVP_CIPHER_CTX_get0_cipher(self.as_ptr()), self.iv_length()) },
+        };
+        assert!(iv_len <= iv.len());
+
+        // The tracker is never left inconsistent, so it remains usable if another user of it panicked.
+        let mut tracker = tracker.lock().unwrap_or_else(PoisonError::into_inner);
+        let aead = unsafe { ffi::EVP_CIPHER_flags(cipher) } & ffi::EVP_CIPH_FLAG_AEAD_CIPHER != 0;
+        let entry = if aead {
+            let entry = tracker.entry(&tracker.key_id(key)?, &iv[..iv_len])?;
+            if tracker.contains(&entry) {
+                return Err(ErrorStack::internal_error(
+                    "nonce has already been used with this key",
+                ));
+            }
+            Some(entry)
+        } else {
+            None
+        };
+
+        self.encrypt_init(type_, Some(key), Some(iv))?;
+        if let Some(entry) = entry {
+            tracker.insert(entry);
+        }
+
+        Ok(())
+    }
+
+    /// Initializes the context for ChaCha20 with the given key, initial block counter and nonce.
+    ///
+    /// OpenSSL's ChaCha20 takes a 16 byte IV made up of the 32 bit block counter in little-endian byte order
+    /// followed by the 96 bit nonce of RFC 8439. This assembles the IV from its parts, avoiding the easy mistake of
+    /// putting the nonce first.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[corresponds(EVP_CipherInit_ex)]
+    #[cfg(all(ossl110, not(osslconf = "OPENSSL_NO_CHACHA")))]
+    pub fn chacha20_init(
+        &mut self,
+        key: &[u8; 32],
+        counter: u32,
+        nonce: &[u8; 12],
+        encrypt: bool,
+    ) -> Result<(), ErrorStack> {
+        let mut iv = [0; 16];
+        iv[..4].copy_from_slice(&counter.to_le_bytes());
+        iv[4..].copy_from_slice(nonce);
+
+        if encrypt {
+            self.encrypt_init(Some(Cipher::chacha20()), Some(key), Some(&iv))
+        } else {
+            self.decrypt_init(Some(Cipher::chacha20()), Some(key), Some(&iv))
+        }
+    }
+
+    /// Fetches the named cipher using the specified property query and initializes the context for encryption.
+    ///
+    /// This can be used to force the selection of a specific provider for the operation, for example with a property
+    /// query of `fips=yes`.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher or the IV buffer is smaller than the IV
+    /// size of the cipher.
+    #[corresponds(EVP_CIPHER_fetch)]
+    #[cfg(ossl300)]
+    pub fn encrypt_init_fetched(
+        &mut self,
+        name: &str,
+        properties: Option<&str>,
+        key: Option<&[u8]>,
+        iv: Option<&[u8]>,
+    ) -> Result<(), ErrorStack> {
+        let cipher = Cipher::fetch(None, name, properties)?;
+        self.encrypt_init(Some(&cipher), key, iv)
+    }
+
+    /// Fetches the named cipher using the specified property query and initializes the context for decryption.
+    ///
+    /// This can be used to force the selection of a specific provider for the operation, for example with a property
+    /// query of `fips=yes`.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher or the IV buffer is smaller than the IV
+    /// size of the cipher.
+    #[corresponds(EVP_CIPHER_fetch)]
+    #[cfg(ossl300)]
+    pub fn decrypt_init_fetched(
+        &mut self,
+        name: &str,
+        properties: Option<&str>,
+        key: Option<&[u8]>,
+        iv: Option<&[u8]>,
+    ) -> Result<(), ErrorStack> {
+        let cipher = Cipher::fetch(None, name, properties)?;
+        self.decrypt_init(Some(&cipher), key, iv)
+    }
+
+    /// Clears the cipher, key, and IV of the context so that it can be initialized again.
+    ///
+    /// This allows a single allocation to be reused for unrelated operations. Afterwards the context is in the same
+    /// state as a newly created one, so methods documented to panic on an uninitialized context will do so until
+    /// [`Self::encrypt_init`] or [`Self::decrypt_init`] is called again.
+    #[corresponds(EVP_CIPHER_CTX_reset)]
+    pub fn reset(&mut self) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(EVP_CIPHER_CTX_reset(self.as_ptr()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new context which duplicates the current state of this one.
+    ///
+    /// The copy carries over the cipher, key schedule, and current IV or counter position, as well as any AAD and
+    /// partially processed block which have been passed to the context so far, so both contexts produce the same
+    /// output when fed identical remaining input. This can be used to set up a key once and then fork the context
+    /// for each message, avoiding repeated key setup.
+    #[corresponds(EVP_CIPHER_CTX_copy)]
+    pub fn try_clone(&self) -> Result<CipherCtx, ErrorStack> {
+        let ctx = CipherCtx::new()?;
+        unsafe {
+            cvt(ffi::EVP_CIPHER_CTX_copy(ctx.as_ptr(), self.as_ptr()))?;
+        }
+
+        Ok(ctx)
+    }
+
     fn cipher_init(
         &mut self,
         type_: Option<&CipherRef>,
@@ -192,6 +737,31 @@ impl CipherCtxRef {
     where
         T: HasPublic,
     {
+        self.seal_init_iter(type_, pub_keys.iter().map(|k| &**k), encrypted_keys, iv)
+    }
+
+    /// Initializes the context to perform envelope encryption to the public keys yielded by `pub_keys`.
+    ///
+    /// This is equivalent to [`Self::seal_init`], but accepts references to the keys, so that keys held elsewhere,
+    /// such as in a map, need not be cloned into a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pub_keys` does not yield as many keys as `encrypted_keys` has elements, the IV buffer is smaller
+    /// than the cipher's IV size, or if an IV is provided before the cipher.
+    #[corresponds(EVP_SealInit)]
+    pub fn seal_init_iter<'a, T, I>(
+        &mut self,
+        type_: Option<&CipherRef>,
+        pub_keys: I,
+        encrypted_keys: &mut [Vec<u8>],
+        iv: Option<&mut [u8]>,
+    ) -> Result<(), ErrorStack>
+    where
+        T: HasPublic + 'a,
+        I: IntoIterator<Item = &'a PKeyRef<T>>,
+    {
+        let pub_keys = pub_keys.into_iter().collect::<Vec<_>>();
         assert_eq!(pub_keys.len(), encrypted_keys.len());
         if !pub_keys.is_empty() {
             let iv_len = type_.map_or_else(|| self.iv_length(), |c| c.iv_length());
@@ -207,6 +777,7 @@ impl CipherCtxRef {
             .map(|b| b.as_mut_ptr())
             .collect::<Vec<_>>();
         let mut key_lengths = vec![0; pub_keys.len()];
+        let mut pub_key_ptrs = pub_keys.iter().map(|k| k.as_ptr()).collect::<Vec<_>>();
         let pub_keys_len = i32::try_from(pub_keys.len()).unwrap();
 
         unsafe {
@@ -216,7 +787,7 @@ impl CipherCtxRef {
                 keys.as_mut_ptr(),
                 key_lengths.as_mut_ptr(),
                 iv.map_or(ptr::null_mut(), |b| b.as_mut_ptr()),
-                pub_keys.as_ptr() as *mut _,
+                pub_key_ptrs.as_mut_ptr(),
                 pub_keys_len,
             ))?;
         }
@@ -324,9 +895,23 @@ impl CipherCtxRef {
         Ok(())
     }
 
+    /// Like [`Self::rand_key`] except that it returns the key in a newly allocated [`Vec`] of the cipher's key
+    /// length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_rand_key)]
+    pub fn rand_key_vec(&self) -> Result<Vec<u8>, ErrorStack> {
+        let mut key = vec![0; self.key_length()];
+        self.rand_key(&mut key)?;
+        Ok(key)
+    }
+
     /// Sets the length of the key expected by the context.
     ///
-    /// Only some ciphers support configurable key lengths.
+    /// Only some ciphers support configurable key lengths. An error is returned if the cipher has a fixed key
+    /// length and `len` differs from it, rather than leaving the context in an inconsistent state.
     ///
     /// # Panics
     ///
@@ -335,6 +920,12 @@ impl CipherCtxRef {
     pub fn set_key_length(&mut self, len: usize) -> Result<(), ErrorStack> {
         self.assert_cipher();
 
+        if !self.has_variable_key_length() && len != self.key_length() {
+            return Err(ErrorStack::internal_error(
+                "cipher does not support variable key lengths",
+            ));
+        }
+
         let len = c_int::try_from(len).unwrap();
 
         unsafe {
@@ -344,6 +935,20 @@ impl CipherCtxRef {
         Ok(())
     }
 
+    fn has_variable_key_length(&self) -> bool {
+        unsafe {
+            let cipher = EVP_CIPHER_CTX_get0_cipher(self.as_ptr());
+            // Provided ciphers do not report EVP_CIPH_VARIABLE_LENGTH, so the flag is read from the built-in cipher
+            // of the same type. Ciphers without a built-in counterpart are left to OpenSSL to validate.
+            #[cfg(ossl300)]
+            let cipher = match Cipher::from_nid(Nid::from_raw(ffi::EVP_CIPHER_nid(cipher))) {
+                Some(cipher) => cipher.as_ptr(),
+                None => return true,
+            };
+            ffi::EVP_CIPHER_flags(cipher) & ffi::EVP_CIPH_VARIABLE_LENGTH != 0
+        }
+    }
+
     /// Returns the length of the IV expected by this context.
     ///
     /// Returns 0 if the cipher does not use an IV.
@@ -383,88 +988,575 @@ impl CipherCtxRef {
         Ok(())
     }
 
-    /// Returns the length of the authentication tag expected by this context.
+    /// Sets the fixed field of the IV for generation with [`Self::iv_gen`].
     ///
-    /// Returns 0 if the cipher is not authenticated.
+    /// The IV is split into `fixed`, which must be at least 4 bytes long, and an invocation field of at least 8
+    /// bytes making up the rest of the IV, as described in RFC 5116 section 3.2. When encrypting, the invocation
+    /// field is initialized to a random value. This must be called after the key is set, and is only supported by
+    /// GCM mode ciphers.
     ///
     /// # Panics
     ///
     /// Panics if the context has not been initialized with a cipher.
-    ///
-    /// Requires OpenSSL 3.0.0 or newer.
-    #[corresponds(EVP_CIPHER_CTX_get_tag_length)]
-    #[cfg(ossl300)]
-    pub fn tag_length(&self) -> usize {
+    #[corresponds(EVP_CIPHER_CTX_ctrl)]
+    pub fn set_iv_fixed(&mut self, fixed: &[u8]) -> Result<(), ErrorStack> {
         self.assert_cipher();
 
-        unsafe { ffi::EVP_CIPHER_CTX_get_tag_length(self.as_ptr()) as usize }
-    }
-
-    /// Retrieves the calculated authentication tag from the context.
-    ///
-    /// This should be called after `[Self::cipher_final]`, and is only supported by authenticated ciphers.
-    ///
-    /// The size of the buffer indicates the size of the tag. While some ciphers support a range of tag sizes, it is
-    /// recommended to pick the maximum size.
-    #[corresponds(EVP_CIPHER_CTX_ctrl)]
-    pub fn tag(&self, tag: &mut [u8]) -> Result<(), ErrorStack> {
-        let len = c_int::try_from(tag.len()).unwrap();
+        let len = c_int::try_from(fixed.len()).unwrap();
 
         unsafe {
             cvt(ffi::EVP_CIPHER_CTX_ctrl(
                 self.as_ptr(),
-                ffi::EVP_CTRL_GCM_GET_TAG,
+                ffi::EVP_CTRL_GCM_SET_IV_FIXED,
                 len,
-                tag.as_mut_ptr() as *mut _,
+                fixed.as_ptr() as *mut _,
             ))?;
         }
 
         Ok(())
     }
 
-    /// Sets the length of the generated authentication tag.
+    /// Starts a new operation with the next IV generated from the fields set with [`Self::set_iv_fixed`].
     ///
-    /// This must be called when encrypting with a cipher in CCM mode to use a tag size other than the default.
+    /// The trailing `out.len()` bytes of the IV, normally the 8-byte invocation field which is sent alongside the
+    /// ciphertext, are written to `out`. The last 8 bytes of the IV are then incremented as a big-endian counter for
+    /// the next call. AAD and payload for the new operation can be passed to the context afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher, or if `out` is empty or longer than the IV.
     #[corresponds(EVP_CIPHER_CTX_ctrl)]
-    pub fn set_tag_length(&mut self, len: usize) -> Result<(), ErrorStack> {
-        let len = c_int::try_from(len).unwrap();
+    pub fn iv_gen(&mut self, out: &mut [u8]) -> Result<(), ErrorStack> {
+        // OpenSSL writes the entire IV if asked for no bytes or more bytes than it has.
+        assert!(!out.is_empty() && out.len() <= self.iv_length());
+
+        let len = c_int::try_from(out.len()).unwrap();
 
         unsafe {
             cvt(ffi::EVP_CIPHER_CTX_ctrl(
                 self.as_ptr(),
-                ffi::EVP_CTRL_GCM_SET_TAG,
+                ffi::EVP_CTRL_GCM_IV_GEN,
                 len,
-                ptr::null_mut(),
+                out.as_mut_ptr() as *mut _,
             ))?;
         }
 
         Ok(())
     }
 
-    /// Sets the authentication tag for verification during decryption.
-    #[corresponds(EVP_CIPHER_CTX_ctrl)]
-    pub fn set_tag(&mut self, tag: &[u8]) -> Result<(), ErrorStack> {
-        let len = c_int::try_from(tag.len()).unwrap();
+    /// Sets parameters of the context's cipher by name.
+    ///
+    /// This reaches settings of provider-based ciphers which have no dedicated method, such as those of new
+    /// algorithms. The names and types of the supported parameters are documented by OpenSSL for each cipher; for
+    /// example, the IV length of AES-GCM is the `size_t` parameter `ivlen`.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_set_params)]
+    #[cfg(ossl300)]
+    pub fn set_params(&mut self, params: &OsslParamArray) -> Result<(), ErrorStack> {
+        self.assert_cipher();
 
         unsafe {
-            cvt(ffi::EVP_CIPHER_CTX_ctrl(
+            cvt(ffi::EVP_CIPHER_CTX_set_params(
                 self.as_ptr(),
-                ffi::EVP_CTRL_GCM_SET_TAG,
-                len,
-                tag.as_ptr() as *mut _,
+                params.as_ptr(),
             ))?;
         }
 
         Ok(())
     }
 
-    /// Enables or disables padding.
+    /// Returns the IV currently held by the context.
     ///
-    /// If padding is disabled, the plaintext must be an exact multiple of the cipher's block size.
-    #[corresponds(EVP_CIPHER_CTX_set_padding)]
-    pub fn set_padding(&mut self, padding: bool) {
-        unsafe {
-            ffi::EVP_CIPHER_CTX_set_padding(self.as_ptr(), padding as c_int);
+    /// This is [`Self::iv_length`] bytes long, so it reflects an IV length set with [`Self::set_iv_length`]. In
+    /// modes such as CBC, CFB, OFB and CTR the IV is updated as data is processed, and the value returned is the one
+    /// which continues the operation. In GCM mode the value is the nonce the operation started with.
+    ///
+    /// Before OpenSSL 3.0.0, the IV of ciphers which manage it themselves, such as GCM, CCM and ChaCha20-Poly1305,
+    /// cannot be retrieved and an error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[corresponds(EVP_CIPHER_CTX_get_updated_iv)]
+    #[cfg(ossl110)]
+    pub fn iv(&self) -> Result<Vec<u8>, ErrorStack> {
+        self.assert_cipher();
+
+        let mut iv = vec![0; self.iv_length()];
+        cfg_if! {
+            if #[cfg(ossl300)] {
+                unsafe {
+                    cvt(ffi::EVP_CIPHER_CTX_get_updated_iv(
+                        self.as_ptr(),
+                        iv.as_mut_ptr() as *mut c_void,
+                        iv.len(),
+                    ))?;
+                }
+            } else {
+                // The context only holds the IV in a fixed-size buffer, which ciphers with a custom IV do not use.
+                let cipher = unsafe { EVP_CIPHER_CTX_get0_cipher(self.as_ptr()) };
+                if unsafe { ffi::EVP_CIPHER_flags(cipher) } & ffi::EVP_CIPH_CUSTOM_IV != 0
+                    || iv.len() > ffi::EVP_MAX_IV_LENGTH as usize
+                {
+                    return Err(ErrorStack::internal_error(
+                        "the IV of this cipher cannot be retrieved before OpenSSL 3.0.0",
+                    ));
+                }
+                unsafe {
+                    let ptr = ffi::EVP_CIPHER_CTX_iv(self.as_ptr());
+                    ptr::copy_nonoverlapping(ptr, iv.as_mut_ptr(), iv.len());
+                }
+            }
+        }
+
+        Ok(iv)
+    }
+
+    /// Returns the length of the authentication tag expected by this context.
+    ///
+    /// Returns 0 if the cipher is not authenticated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    #[corresponds(EVP_CIPHER_CTX_get_tag_length)]
+    #[cfg(ossl300)]
+    pub fn tag_length(&self) -> usize {
+        self.assert_cipher();
+
+        unsafe { ffi::EVP_CIPHER_CTX_get_tag_length(self.as_ptr()) as usize }
+    }
+
+    /// Returns `true` if the context has been initialized for encryption, and `false` if it has been initialized for
+    /// decryption.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_encrypting)]
+    pub fn is_encrypting(&self) -> bool {
+        self.assert_cipher();
+
+        #[cfg(any(ossl110, libressl273))]
+        unsafe {
+            ffi::EVP_CIPHER_CTX_encrypting(self.as_ptr()) != 0
+        }
+        // The context is not opaque in older versions, and starts with the fields mirrored here.
+        #[cfg(not(any(ossl110, libressl273)))]
+        unsafe {
+            (*(self.as_ptr() as *const CipherCtxPrefix)).encrypt != 0
+        }
+    }
+
+    /// Returns the name of the context's cipher, such as `AES-128-CBC`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_get0_name)]
+    pub fn cipher_name(&self) -> &str {
+        self.assert_cipher();
+
+        unsafe {
+            let name = ffi::EVP_CIPHER_name(EVP_CIPHER_CTX_get0_cipher(self.as_ptr()));
+            CStr::from_ptr(name).to_str().unwrap()
+        }
+    }
+
+    /// Returns the name of the provider supplying the context's cipher, such as `default`, `fips` or `legacy`.
+    ///
+    /// Returns `None` if the context has not been initialized with a cipher, or on OpenSSL versions before 3.0.0,
+    /// which have no providers.
+    #[corresponds(EVP_CIPHER_get0_provider)]
+    pub fn provider_name(&self) -> Option<String> {
+        #[cfg(ossl300)]
+        unsafe {
+            let cipher = EVP_CIPHER_CTX_get0_cipher(self.as_ptr());
+            if cipher.is_null() {
+                return None;
+            }
+            let provider = ffi::EVP_CIPHER_get0_provider(cipher);
+            if provider.is_null() {
+                return None;
+            }
+            let name = ffi::OSSL_PROVIDER_get0_name(provider);
+            if name.is_null() {
+                return None;
+            }
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+        }
+        #[cfg(not(ossl300))]
+        None
+    }
+
+    /// Sets the effective key length of an RC2 cipher in bits.
+    ///
+    /// RC2 limits the strength of its key schedule to this number of bits independently of the length of the key,
+    /// which some legacy systems rely on. It must be called after the cipher is set but before the key is, as the
+    /// key schedule is derived when the key is set. It has no meaning for ciphers other than RC2, for which an
+    /// error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_ctrl)]
+    pub fn set_rc2_key_bits(&mut self, bits: usize) -> Result<(), ErrorStack> {
+        self.check_rc2()?;
+
+        let bits = c_int::try_from(bits).unwrap();
+
+        unsafe {
+            cvt(ffi::EVP_CIPHER_CTX_ctrl(
+                self.as_ptr(),
+                ffi::EVP_CTRL_SET_RC2_KEY_BITS,
+                bits,
+                ptr::null_mut(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the effective key length of an RC2 cipher in bits.
+    ///
+    /// An error is returned for ciphers other than RC2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_ctrl)]
+    pub fn rc2_key_bits(&self) -> Result<usize, ErrorStack> {
+        self.check_rc2()?;
+
+        // The control reports success without retrieving the value on OpenSSL 3.0, so the parameter is read
+        // directly instead.
+        #[cfg(ossl300)]
+        let bits = unsafe {
+            let mut bits: size_t = 0;
+            let mut params = [
+                ffi::OSSL_PARAM_construct_size_t(b"keybits\0".as_ptr() as *const c_char, &mut bits),
+                ffi::OSSL_PARAM_construct_end(),
+            ];
+            cvt(ffi::EVP_CIPHER_CTX_get_params(
+                self.as_ptr(),
+                params.as_mut_ptr(),
+            ))?;
+            bits
+        };
+        #[cfg(not(ossl300))]
+        let bits = unsafe {
+            let mut bits: c_int = 0;
+            cvt(ffi::EVP_CIPHER_CTX_ctrl(
+                self.as_ptr(),
+                ffi::EVP_CTRL_GET_RC2_KEY_BITS,
+                0,
+                &mut bits as *mut c_int as *mut c_void,
+            ))?;
+            bits
+        };
+
+        Ok(bits as usize)
+    }
+
+    // OpenSSL 3.0 ignores the RC2 parameters for other ciphers rather than reporting an error.
+    fn check_rc2(&self) -> Result<(), ErrorStack> {
+        self.assert_cipher();
+
+        let nid = unsafe { ffi::EVP_CIPHER_nid(EVP_CIPHER_CTX_get0_cipher(self.as_ptr())) };
+        match nid {
+            ffi::NID_rc2_cbc
+            | ffi::NID_rc2_ecb
+            | ffi::NID_rc2_cfb64
+            | ffi::NID_rc2_ofb64
+            | ffi::NID_rc2_40_cbc
+            | ffi::NID_rc2_64_cbc => Ok(()),
+            _ => Err(ErrorStack::internal_error("cipher is not an RC2 cipher")),
+        }
+    }
+
+    /// Retrieves the calculated authentication tag from the context.
+    ///
+    /// This should be called after `[Self::cipher_final]`, and is only supported by authenticated ciphers.
+    ///
+    /// The size of the buffer indicates the size of the tag. While some ciphers support a range of tag sizes, it is
+    /// recommended to pick the maximum size.
+    #[corresponds(EVP_CIPHER_CTX_ctrl)]
+    pub fn tag(&self, tag: &mut [u8]) -> Result<(), ErrorStack> {
+        let len = c_int::try_from(tag.len()).unwrap();
+
+        unsafe {
+            cvt(ffi::EVP_CIPHER_CTX_ctrl(
+                self.as_ptr(),
+                ffi::EVP_CTRL_GCM_GET_TAG,
+                len,
+                tag.as_mut_ptr() as *mut _,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the mode of operation of the context's cipher.
+    ///
+    /// This can be used to check that a context uses an authenticated mode before calling [`Self::set_tag`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_mode)]
+    pub fn cipher_mode(&self) -> CipherMode {
+        self.assert_cipher();
+
+        let cipher = unsafe { EVP_CIPHER_CTX_get0_cipher(self.as_ptr()) };
+        match unsafe { ffi::EVP_CIPHER_flags(cipher) } & ffi::EVP_CIPH_MODE {
+            ffi::EVP_CIPH_ECB_MODE => CipherMode::Ecb,
+            ffi::EVP_CIPH_CBC_MODE => CipherMode::Cbc,
+            ffi::EVP_CIPH_CFB_MODE => CipherMode::Cfb,
+            ffi::EVP_CIPH_OFB_MODE => CipherMode::Ofb,
+            ffi::EVP_CIPH_CTR_MODE => CipherMode::Ctr,
+            ffi::EVP_CIPH_GCM_MODE => CipherMode::Gcm,
+            ffi::EVP_CIPH_CCM_MODE => CipherMode::Ccm,
+            ffi::EVP_CIPH_XTS_MODE => CipherMode::Xts,
+            ffi::EVP_CIPH_WRAP_MODE => CipherMode::Wrap,
+            ffi::EVP_CIPH_OCB_MODE => CipherMode::Ocb,
+            ffi::EVP_CIPH_STREAM_CIPHER => CipherMode::Stream,
+            _ => CipherMode::Other,
+        }
+    }
+
+    /// Returns the authentication tag lengths supported by the context's cipher, in ascending order.
+    ///
+    /// The lengths are derived from the cipher's mode: GCM accepts 4, 8, and 12 to 16 bytes, CCM accepts even
+    /// lengths from 4 to 16 bytes, SIV requires 16 bytes, and OCB and ChaCha20-Poly1305 accept any length up to 16
+    /// bytes. An empty list is returned for ciphers which are not authenticated, or whose mode is not recognized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_flags)]
+    pub fn valid_tag_lengths(&self) -> Vec<usize> {
+        self.assert_cipher();
+
+        let cipher = unsafe { EVP_CIPHER_CTX_get0_cipher(self.as_ptr()) };
+        match unsafe { ffi::EVP_CIPHER_flags(cipher) } & ffi::EVP_CIPH_MODE {
+            ffi::EVP_CIPH_GCM_MODE => vec![4, 8, 12, 13, 14, 15, 16],
+            ffi::EVP_CIPH_CCM_MODE => vec![4, 6, 8, 10, 12, 14, 16],
+            ffi::EVP_CIPH_OCB_MODE => (1..=16).collect(),
+            ffi::EVP_CIPH_SIV_MODE => vec![16],
+            #[cfg(ossl110)]
+            _ if unsafe { ffi::EVP_CIPHER_nid(cipher) } == ffi::NID_chacha20_poly1305 => {
+                (1..=16).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Sets the length of the generated authentication tag.
+    ///
+    /// This must be called when encrypting with a cipher in CCM mode to use a tag size other than the default.
+    ///
+    /// Lengths not included in [`Self::valid_tag_lengths`] are rejected for authenticated ciphers whose mode is
+    /// recognized.
+    #[corresponds(EVP_CIPHER_CTX_ctrl)]
+    pub fn set_tag_length(&mut self, len: usize) -> Result<(), ErrorStack> {
+        let has_cipher = unsafe { !EVP_CIPHER_CTX_get0_cipher(self.as_ptr()).is_null() };
+        if has_cipher {
+            let valid = self.valid_tag_lengths();
+            if !valid.is_empty() && !valid.contains(&len) {
+                return Err(ErrorStack::internal_error(&format!(
+                    "unsupported tag length {}",
+                    len
+                )));
+            }
+        }
+
+        let len = c_int::try_from(len).unwrap();
+
+        unsafe {
+            cvt(ffi::EVP_CIPHER_CTX_ctrl(
+                self.as_ptr(),
+                ffi::EVP_CTRL_GCM_SET_TAG,
+                len,
+                ptr::null_mut(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the authentication tag for verification during decryption.
+    #[corresponds(EVP_CIPHER_CTX_ctrl)]
+    pub fn set_tag(&mut self, tag: &[u8]) -> Result<(), ErrorStack> {
+        let len = c_int::try_from(tag.len()).unwrap();
+
+        unsafe {
+            cvt(ffi::EVP_CIPHER_CTX_ctrl(
+                self.as_ptr(),
+                ffi::EVP_CTRL_GCM_SET_TAG,
+                len,
+                tag.as_ptr() as *mut _,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Enables or disables padding.
+    ///
+    /// If padding is disabled, the plaintext must be an exact multiple of the cipher's block size.
+    #[corresponds(EVP_CIPHER_CTX_set_padding)]
+    pub fn set_padding(&mut self, padding: bool) {
+        unsafe {
+            ffi::EVP_CIPHER_CTX_set_padding(self.as_ptr(), padding as c_int);
+        }
+    }
+
+    /// Returns whether padding is enabled.
+    ///
+    /// Padding is enabled by default, and can be disabled with [`Self::set_padding`]. It only has an effect on block
+    /// ciphers.
+    #[corresponds(EVP_CIPHER_CTX_test_flags)]
+    pub fn padding(&self) -> bool {
+        unsafe { ffi::EVP_CIPHER_CTX_test_flags(self.as_ptr(), ffi::EVP_CIPH_NO_PADDING) == 0 }
+    }
+
+    /// Sets the given flags on the context, leaving all others unchanged.
+    ///
+    /// See [`CipherCtxFlags`] for when each flag must be set to take effect.
+    #[corresponds(EVP_CIPHER_CTX_set_flags)]
+    pub fn set_flags(&mut self, flags: CipherCtxFlags) {
+        unsafe {
+            ffi::EVP_CIPHER_CTX_set_flags(self.as_ptr(), flags.bits());
+        }
+    }
+
+    /// Clears the given flags on the context, leaving all others unchanged.
+    #[corresponds(EVP_CIPHER_CTX_clear_flags)]
+    pub fn clear_flags(&mut self, flags: CipherCtxFlags) {
+        unsafe {
+            ffi::EVP_CIPHER_CTX_clear_flags(self.as_ptr(), flags.bits());
+        }
+    }
+
+    /// Returns those of the given flags which are set on the context.
+    #[corresponds(EVP_CIPHER_CTX_test_flags)]
+    pub fn test_flags(&self, flags: CipherCtxFlags) -> CipherCtxFlags {
+        let bits = unsafe { ffi::EVP_CIPHER_CTX_test_flags(self.as_ptr(), flags.bits()) };
+        CipherCtxFlags::from_bits_truncate(bits)
+    }
+
+    /// Returns the number of bytes of the current block which have been processed.
+    ///
+    /// This is only meaningful for the stream-like CFB, OFB and CTR modes, where it is the offset into the current
+    /// block of keystream. Together with the IV, it determines the position of the operation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[corresponds(EVP_CIPHER_CTX_num)]
+    #[cfg(ossl110)]
+    pub fn num(&self) -> usize {
+        self.assert_cipher();
+
+        let num = unsafe { ffi::EVP_CIPHER_CTX_num(self.as_ptr()) };
+        cmp::max(num, 0) as usize
+    }
+
+    /// Sets the number of bytes of the current block which have been processed.
+    ///
+    /// This is only meaningful for the stream-like CFB, OFB and CTR modes. Combined with initializing the context
+    /// with the IV recorded alongside [`Self::num`], it resumes an operation at an arbitrary byte offset. In CFB and
+    /// OFB modes the IV holds the keystream state itself, but in CTR mode the partially used block of keystream is
+    /// not part of the IV, so it must be regenerated instead, for example by processing `n` dummy bytes under the
+    /// previous counter value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher, or if `n` is not smaller than the block size of
+    /// the underlying block cipher.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[corresponds(EVP_CIPHER_CTX_set_num)]
+    #[cfg(ossl110)]
+    pub fn set_num(&mut self, n: usize) {
+        self.assert_cipher();
+        assert!(n < cmp::max(self.iv_length(), 1));
+
+        unsafe {
+            ffi::EVP_CIPHER_CTX_set_num(self.as_ptr(), n as c_int);
+        }
+    }
+
+    /// Checks that `total_input` bytes can be processed by the context without padding.
+    ///
+    /// If padding has been disabled on a block cipher, the total input passed to [`Self::cipher_update`] must be an
+    /// exact multiple of the block size, or [`Self::cipher_final`] will fail. Calling this method beforehand detects
+    /// truncated or extraneous input with a descriptive error. It always succeeds for stream ciphers.
+    ///
+    /// When encrypting with padding enabled, any input length is valid, as the final block is completed with padding,
+    /// so this method succeeds.
+    ///
+    /// When decrypting with padding enabled, the ciphertext must still be a non-empty multiple of the block size, but
+    /// this method does not check it and succeeds regardless. Misaligned ciphertext is instead rejected by
+    /// [`Self::cipher_final`] along with invalid padding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_test_flags)]
+    pub fn check_alignment(&self, total_input: usize) -> Result<(), ErrorStack> {
+        let block_size = self.block_size();
+        let trailing = total_input % block_size;
+
+        if !self.padding() && trailing != 0 {
+            return Err(ErrorStack::internal_error(&format!(
+                "input length {} is not a multiple of the block size {} and padding is disabled",
+                total_input, block_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of bytes the call to [`Self::cipher_final`] will write.
+    ///
+    /// When encrypting with a padded block cipher, the final call writes the bytes which did not fill a block
+    /// followed by enough PKCS#7 padding to complete it, which is always exactly one block regardless of the length
+    /// of the input. When decrypting with padding, the amount of padding is not known until the final block has
+    /// been decrypted, so the maximum of one byte less than the block size is returned. In all other cases, nothing
+    /// is written.
+    ///
+    /// [`CipherSession::final_output_len`] additionally accounts for length-hiding padding, given the total length
+    /// of the input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[corresponds(EVP_CIPHER_CTX_encrypting)]
+    #[cfg(ossl110)]
+    pub fn cipher_final_len(&self) -> usize {
+        let block_size = self.block_size();
+        let block_padding = block_size > 1 && self.padding();
+
+        if !block_padding {
+            0
+        } else if unsafe { ffi::EVP_CIPHER_CTX_encrypting(self.as_ptr()) } != 0 {
+            block_size
+        } else {
+            block_size - 1
         }
     }
 
@@ -496,28 +1588,49 @@ impl CipherCtxRef {
     ///
     /// # Panics
     ///
-    /// Panics if `output.len()` is less than `input.len()` plus the cipher's block size.
+    /// Panics if `output.len()` is less than `input.len()` plus the cipher's block size, or twice the block size for
+    /// key wrap ciphers.
     #[corresponds(EVP_CipherUpdate)]
     pub fn cipher_update(
         &mut self,
         input: &[u8],
         output: Option<&mut [u8]>,
     ) -> Result<usize, ErrorStack> {
+        match self.try_cipher_update(input, output) {
+            Ok(len) => Ok(len),
+            Err(CipherError::OutputTooSmall { needed, got }) => panic!(
+                "output buffer too small: {} bytes needed, {} provided",
+                needed, got
+            ),
+            Err(CipherError::ErrorStack(e)) => Err(e),
+        }
+    }
+
+    /// Like [`Self::cipher_update`] except that an output buffer which is too small is reported as
+    /// [`CipherError::OutputTooSmall`] rather than causing a panic.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn try_cipher_update(
+        &mut self,
+        input: &[u8],
+        mut output: Option<&mut [u8]>,
+    ) -> Result<usize, CipherError> {
         let inlen = c_int::try_from(input.len()).unwrap();
 
         if let Some(output) = &output {
-            let mut block_size = self.block_size();
-            if block_size == 1 {
-                block_size = 0;
+            let needed = input.len() + self.update_overhead();
+            if output.len() < needed {
+                return Err(CipherError::OutputTooSmall {
+                    needed,
+                    got: output.len(),
+                });
             }
-            assert!(output.len() >= input.len() + block_size);
         }
 
         let mut outlen = 0;
         unsafe {
             cvt(ffi::EVP_CipherUpdate(
                 self.as_ptr(),
-                output.map_or(ptr::null_mut(), |b| b.as_mut_ptr()),
+                output.as_mut().map_or(ptr::null_mut(), |b| b.as_mut_ptr()),
                 &mut outlen,
                 input.as_ptr(),
                 inlen,
@@ -527,20 +1640,342 @@ impl CipherCtxRef {
         Ok(outlen as usize)
     }
 
-    /// Like [`Self::cipher_update`] except that it appends output to a [`Vec`].
-    pub fn cipher_update_vec(
-        &mut self,
-        input: &[u8],
-        output: &mut Vec<u8>,
-    ) -> Result<usize, ErrorStack> {
-        let base = output.len();
-        output.resize(base + input.len() + self.block_size(), 0);
-        let len = self.cipher_update(input, Some(&mut output[base..]))?;
-        output.truncate(base + len);
+    // Returns the number of bytes by which the output of an update may exceed its input.
+    fn update_overhead(&self) -> usize {
+        match self.block_size() {
+            1 => 0,
+            // Padded key wrap rounds the input up to a whole block before prepending another.
+            block_size if self.cipher_mode() == CipherMode::Wrap => 2 * block_size,
+            block_size => block_size,
+        }
+    }
+
+    /// Feeds `aad` into the context as additional authenticated data.
+    ///
+    /// This is equivalent to calling [`Self::cipher_update`] with no output buffer. It must be called after
+    /// [`Self::encrypt_init`] or [`Self::decrypt_init`], and before any payload is passed to the context. In CCM
+    /// mode, [`Self::set_data_len`] must be called first.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn set_aad(&mut self, aad: &[u8]) -> Result<(), ErrorStack> {
+        self.cipher_update(aad, None)?;
+        Ok(())
+    }
+
+    /// Feeds `header` into the context as additional authenticated data, returning a handle which completes the
+    /// message.
+    ///
+    /// This expresses the common pattern of authenticating a header and encrypting a body as a chain:
+    ///
+    /// ```
+    /// use openssl::cipher::Cipher;
+    /// use openssl::cipher_ctx::CipherCtx;
+    ///
+    /// let mut ctx = CipherCtx::new().unwrap();
+    /// ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&[0; 16]), Some(&[0; 12]))
+    ///     .unwrap();
+    /// let (ciphertext, tag) = ctx.authenticate(b"header").unwrap().encrypt(b"body").unwrap();
+    /// ```
+    ///
+    /// As AAD must precede the payload, this must be called before any payload is passed to the context.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn authenticate(&mut self, header: &[u8]) -> Result<Authenticated<'_>, ErrorStack> {
+        self.set_aad(header)?;
+        Ok(Authenticated { ctx: self })
+    }
+
+    fn is_aead(&self) -> bool {
+        unsafe {
+            let cipher = EVP_CIPHER_CTX_get0_cipher(self.as_ptr());
+            !cipher.is_null() && ffi::EVP_CIPHER_flags(cipher) & ffi::EVP_CIPH_FLAG_AEAD_CIPHER != 0
+        }
+    }
+
+    /// Like [`Self::cipher_update`] except that it encrypts or decrypts the first `inlen` bytes of `data` in place.
+    ///
+    /// This avoids a separate output buffer, and is only supported by ciphers with a block size of 1, such as
+    /// stream ciphers and the CTR, GCM and ChaCha20-Poly1305 modes, which write exactly as much output as they read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cipher's block size is not 1 or if `inlen` is greater than `data.len()`.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn cipher_update_inplace(
+        &mut self,
+        data: &mut [u8],
+        inlen: usize,
+    ) -> Result<(), ErrorStack> {
+        assert!(
+            self.block_size() == 1,
+            "in-place processing requires a cipher with a block size of 1"
+        );
+        assert!(inlen <= data.len());
+
+        let inlen = c_int::try_from(inlen).unwrap();
+
+        let mut outlen = 0;
+        unsafe {
+            cvt(ffi::EVP_CipherUpdate(
+                self.as_ptr(),
+                data.as_mut_ptr(),
+                &mut outlen,
+                data.as_ptr(),
+                inlen,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Encrypts a data unit, such as a disk sector, in place with an XTS mode cipher.
+    ///
+    /// The context must have been initialized for encryption with an XTS cipher, such as
+    /// [`Cipher::aes_128_xts`], and its key. The tweak is the data unit number `sector`, encoded little-endian into
+    /// the 16-byte IV as specified by IEEE 1619. XTS cannot continue a data unit across calls, so all of `data` is
+    /// encrypted at once, after which the context can be used for the next data unit.
+    ///
+    /// An error is returned if `data` is shorter than one 16-byte block, which XTS cannot process.
+    ///
+    /// [`Cipher::aes_128_xts`]: crate::cipher::Cipher::aes_128_xts
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn encrypt_sector(&mut self, sector: u64, data: &mut [u8]) -> Result<(), ErrorStack> {
+        self.xts_sector(sector, data, true)
+    }
+
+    /// Decrypts a data unit encrypted by [`Self::encrypt_sector`] in place.
+    ///
+    /// The context must have been initialized for decryption with the same cipher and key.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn decrypt_sector(&mut self, sector: u64, data: &mut [u8]) -> Result<(), ErrorStack> {
+        self.xts_sector(sector, data, false)
+    }
+
+    fn xts_sector(
+        &mut self,
+        sector: u64,
+        data: &mut [u8],
+        encrypting: bool,
+    ) -> Result<(), ErrorStack> {
+        if self.cipher_mode() != CipherMode::Xts {
+            return Err(ErrorStack::internal_error("cipher is not an XTS cipher"));
+        }
+        // Setting only the IV keeps the key schedule, which differs between encryption and decryption.
+        if self.is_encrypting() != encrypting {
+            return Err(ErrorStack::internal_error(if encrypting {
+                "context is not initialized for encryption"
+            } else {
+                "context is not initialized for decryption"
+            }));
+        }
+        if data.len() < XTS_BLOCK_LEN {
+            return Err(ErrorStack::internal_error(
+                "XTS requires at least one block of data",
+            ));
+        }
+
+        let mut iv = [0; XTS_BLOCK_LEN];
+        iv[..8].copy_from_slice(&sector.to_le_bytes());
+        if encrypting {
+            self.encrypt_init(None, None, Some(&iv))?;
+        } else {
+            self.decrypt_init(None, None, Some(&iv))?;
+        }
+
+        let len = data.len();
+        self.cipher_update_inplace(data, len)
+    }
+
+    /// Like [`Self::cipher_update`] except that it appends output to a [`Vec`].
+    pub fn cipher_update_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<usize, ErrorStack> {
+        let base = output.len();
+        output.resize(base + input.len() + self.update_overhead(), 0);
+        let len = self.cipher_update(input, Some(&mut output[base..]))?;
+        output.truncate(base + len);
+
+        Ok(len)
+    }
+
+    /// Like [`Self::cipher_update_vec`] except that it processes the concatenation of `bufs`.
+    ///
+    /// The slices are fed through the context in order without being copied into one buffer, so a partial block at
+    /// the end of one slice is completed by the next just as with separate calls. Returns the number of bytes
+    /// appended to `output`.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn cipher_update_vectored(
+        &mut self,
+        bufs: &[&[u8]],
+        output: &mut Vec<u8>,
+    ) -> Result<usize, ErrorStack> {
+        let total = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        output.reserve(total + self.update_overhead());
+
+        let mut len = 0;
+        for buf in bufs {
+            len += self.cipher_update_vec(buf, output)?;
+        }
+
+        Ok(len)
+    }
+
+    /// Like [`Self::cipher_update`] except that it writes output to `writer`.
+    ///
+    /// The input is processed in chunks through a fixed-size stack buffer, so memory use is bounded regardless of the
+    /// length of `input`. Returns the number of bytes written.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn cipher_update_to_writer<W>(&mut self, input: &[u8], writer: &mut W) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        let mut buf = [0; 8192];
+        let chunk_len = buf.len() - self.update_overhead();
+        let mut total = 0;
+        for chunk in input.chunks(chunk_len) {
+            let len = self.cipher_update(chunk, Some(&mut buf))?;
+            writer.write_all(&buf[..len])?;
+            total += len;
+        }
+
+        Ok(total)
+    }
+
+    /// Like [`Self::cipher_update_vec`] except that it also feeds data into `hasher`.
+    ///
+    /// If `hash_input` is `true`, `input` is hashed, and otherwise the output appended to `out` is. This allows for
+    /// example hashing the ciphertext while encrypting or decrypting in a single pass over the data.
+    ///
+    /// Returns the number of bytes appended to `out`.
+    pub fn cipher_update_with_hash(
+        &mut self,
+        input: &[u8],
+        out: &mut Vec<u8>,
+        hasher: &mut Hasher,
+        hash_input: bool,
+    ) -> Result<usize, ErrorStack> {
+        let base = out.len();
+        let len = self.cipher_update_vec(input, out)?;
+        if hash_input {
+            hasher.update(input)?;
+        } else {
+            hasher.update(&out[base..])?;
+        }
 
         Ok(len)
     }
 
+    /// Like [`Self::cipher_update`] except that it writes output into a ring buffer.
+    ///
+    /// Output which wraps around the end of the ring buffer is split between its two writable regions, and the
+    /// number of bytes written is committed to the buffer. Data is encrypted in place into the ring buffer except
+    /// for at most one block on each side of the wrap-around point.
+    ///
+    /// Returns the number of bytes written. An error is returned without processing any input if the ring buffer
+    /// has less than `input.len() + block_size` bytes free.
+    pub fn cipher_update_ring<R>(&mut self, input: &[u8], ring: &mut R) -> Result<usize, ErrorStack>
+    where
+        R: RingBuffer + ?Sized,
+    {
+        let block_size = match self.block_size() {
+            1 => 0,
+            block_size => block_size,
+        };
+
+        let written = {
+            let (first, second) = ring.writable();
+            if first.len() + second.len() < input.len() + block_size {
+                return Err(ErrorStack::internal_error(
+                    "insufficient ring buffer capacity",
+                ));
+            }
+
+            let mut input = input;
+            let mut written = 0;
+            while !input.is_empty() {
+                let (region, offset) = if written < first.len() {
+                    (&mut *first, written)
+                } else {
+                    (&mut *second, written - first.len())
+                };
+                let free = region.len() - offset;
+
+                if free > block_size {
+                    let len = cmp::min(input.len(), free - block_size);
+                    written += self.cipher_update(&input[..len], Some(&mut region[offset..]))?;
+                    input = &input[len..];
+                } else {
+                    // not enough room for a block in this region, so go through a temporary buffer and split it
+                    let len = cmp::min(input.len(), cmp::max(block_size, 1));
+                    let mut buf = vec![0; len + block_size];
+                    let out_len = self.cipher_update(&input[..len], Some(&mut buf))?;
+                    input = &input[len..];
+
+                    let mut out = &buf[..out_len];
+                    while !out.is_empty() {
+                        let (region, offset) = if written < first.len() {
+                            (&mut *first, written)
+                        } else {
+                            (&mut *second, written - first.len())
+                        };
+                        let n = cmp::min(out.len(), region.len() - offset);
+                        region[offset..offset + n].copy_from_slice(&out[..n]);
+                        out = &out[n..];
+                        written += n;
+                    }
+                }
+            }
+
+            written
+        };
+        ring.commit(written);
+
+        Ok(written)
+    }
+
+    /// Like [`Self::cipher_update`] except that it replaces the contents of a [`Vec`] with the output.
+    ///
+    /// `output` is cleared but its allocation is kept, and it is only grown if it cannot hold the output. When
+    /// processing many messages, reusing the same buffer therefore avoids allocating for each one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use openssl::cipher::Cipher;
+    /// use openssl::cipher_ctx::CipherCtx;
+    ///
+    /// let mut ctx = CipherCtx::new().unwrap();
+    /// ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&[0; 16]), Some(&[0; 16]))
+    ///     .unwrap();
+    ///
+    /// let mut buf = Vec::with_capacity(64);
+    /// for message in &[&b"first"[..], b"second", b"third"] {
+    ///     ctx.cipher_update_reuse(message, &mut buf).unwrap();
+    ///     assert_eq!(buf.len(), message.len());
+    /// }
+    /// assert_eq!(buf.capacity(), 64);
+    /// ```
+    pub fn cipher_update_reuse(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<usize, ErrorStack> {
+        output.clear();
+        self.cipher_update_vec(input, output)
+    }
+
+    /// Returns an adapter which processes data through the context using `scratch` for all of its output.
+    ///
+    /// Each call on the adapter clears `scratch` and writes its output there, only growing the buffer if it is too
+    /// small, so that a buffer handed over once is reused for every operation without further allocation.
+    ///
+    /// The adapter mutably borrows both the context and `scratch` for as long as it is alive, and the output
+    /// returned by each call borrows the adapter until the next call, so the contents of the buffer can never be
+    /// observed while being overwritten. Once the adapter is dropped, `scratch` holds the output of the last call.
+    pub fn with_scratch<'a>(&'a mut self, scratch: &'a mut Vec<u8>) -> ScratchCipher<'a> {
+        ScratchCipher { ctx: self, scratch }
+    }
+
     /// Finalizes the encryption or decryption process.
     ///
     /// Any remaining data will be written to the output buffer.
@@ -578,88 +2013,4208 @@ impl CipherCtxRef {
 
         Ok(len)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::cipher::Cipher;
-    use std::slice;
+    /// Like [`Self::cipher_final`] except that it writes output to `writer`.
+    ///
+    /// Returns the number of bytes written.
+    #[corresponds(EVP_CipherFinal)]
+    pub fn cipher_final_to_writer<W>(&mut self, writer: &mut W) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        let mut buf = vec![0; self.block_size()];
+        let len = self.cipher_final(&mut buf)?;
+        writer.write_all(&buf[..len])?;
 
-    #[test]
-    fn seal_open() {
-        let private_pem = include_bytes!("../test/rsa.pem");
-        let public_pem = include_bytes!("../test/rsa.pem.pub");
-        let private_key = PKey::private_key_from_pem(private_pem).unwrap();
-        let public_key = PKey::public_key_from_pem(public_pem).unwrap();
-        let cipher = Cipher::aes_256_cbc();
-        let secret = b"My secret message";
+        Ok(len)
+    }
 
-        let mut ctx = CipherCtx::new().unwrap();
-        let mut encrypted_key = vec![];
-        let mut iv = vec![0; cipher.iv_length()];
-        let mut encrypted = vec![];
-        ctx.seal_init(
-            Some(cipher),
-            &[public_key],
-            slice::from_mut(&mut encrypted_key),
-            Some(&mut iv),
-        )
-        .unwrap();
-        ctx.cipher_update_vec(secret, &mut encrypted).unwrap();
-        ctx.cipher_final_vec(&mut encrypted).unwrap();
+    /// Like [`Self::cipher_final`] except that it returns the output in a newly allocated [`Vec`].
+    ///
+    /// The returned buffer contains only the data produced by the final step.
+    pub fn cipher_final_auto(&mut self) -> Result<Vec<u8>, ErrorStack> {
+        let mut output = vec![];
+        self.cipher_final_vec(&mut output)?;
 
-        let mut decrypted = vec![];
-        ctx.open_init(Some(cipher), &encrypted_key, Some(&iv), Some(&private_key))
-            .unwrap();
-        ctx.cipher_update_vec(&encrypted, &mut decrypted).unwrap();
-        ctx.cipher_final_vec(&mut decrypted).unwrap();
+        Ok(output)
+    }
 
-        assert_eq!(secret, &decrypted[..]);
+    /// Finalizes the current record and prepares the context for the next one.
+    ///
+    /// Returns the output of the final step along with, when encrypting with an authenticated cipher, the
+    /// authentication tag. When decrypting, the expected tag must be set with [`Self::set_tag`] beforehand as usual,
+    /// and `None` is returned in its place.
+    ///
+    /// The context is then reinitialized with the same cipher and key, and with `next_iv` as the IV of the next
+    /// record. Each record must use a fresh IV, as reusing an IV with the same key repeats the keystream of modes such
+    /// as CTR, OFB and CFB, and breaks the security guarantees of authenticated ciphers. For ciphers without an IV,
+    /// such as ECB mode ciphers, `next_iv` may be empty.
+    ///
+    /// On OpenSSL versions before 3.0.0, tags are assumed to be 16 bytes long.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher, or if `next_iv` is smaller than the IV size of
+    /// the cipher.
+    ///
+    /// Requires OpenSSL 1.1.0 or newer.
+    #[corresponds(EVP_CipherFinal)]
+    #[cfg(ossl110)]
+    pub fn finalize_record(
+        &mut self,
+        next_iv: &[u8],
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), ErrorStack> {
+        assert!(self.iv_length() <= next_iv.len());
+
+        let output = self.cipher_final_auto()?;
+
+        let (aead, encrypting) = unsafe {
+            let cipher = EVP_CIPHER_CTX_get0_cipher(self.as_ptr());
+            (
+                ffi::EVP_CIPHER_flags(cipher) & ffi::EVP_CIPH_FLAG_AEAD_CIPHER != 0,
+                ffi::EVP_CIPHER_CTX_encrypting(self.as_ptr()) != 0,
+            )
+        };
+        let tag = if aead && encrypting {
+            Some(self.tag_vec()?)
+        } else {
+            None
+        };
+
+        unsafe {
+            cvt(ffi::EVP_CipherInit_ex(
+                self.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null(),
+                next_iv.as_ptr(),
+                -1,
+            ))?;
+        }
+
+        Ok((output, tag))
     }
 
-    fn aes_128_cbc(cipher: &CipherRef) {
-        // from https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf
-        let key = hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
-        let iv = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
-        let pt = hex::decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51")
-            .unwrap();
-        let ct = hex::decode("7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2")
-            .unwrap();
+    /// Encrypts each of `records` with an authenticated cipher, returning the ciphertext and tag of each.
+    ///
+    /// The context is initialized with `cipher` and `key`. The nonce of the first record is `base_nonce`, and it is
+    /// incremented as a big-endian integer for each following record, so every record is sealed under a distinct
+    /// nonce. An error is returned without encrypting anything if the nonce would wrap around. `aad` is
+    /// authenticated with every record.
+    ///
+    /// The caller must ensure that nonces in the range used are never reused with the same key, for example by
+    /// continuing from the nonce after the last record for the next batch.
+    ///
+    /// On OpenSSL versions before 3.0.0, tags are assumed to be 16 bytes long.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher.
+    #[allow(clippy::type_complexity)]
+    pub fn seal_records(
+        &mut self,
+        cipher: &CipherRef,
+        key: &[u8],
+        base_nonce: &[u8],
+        records: &[&[u8]],
+        aad: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ErrorStack> {
+        let mut nonces = Vec::with_capacity(records.len());
+        let mut nonce = base_nonce.to_vec();
+        for i in 0..records.len() {
+            if i > 0 && !increment_be(&mut nonce) {
+                return Err(ErrorStack::internal_error("record nonce would wrap around"));
+            }
+            nonces.push(nonce.clone());
+        }
 
-        let mut ctx = CipherCtx::new().unwrap();
+        self.encrypt_init(Some(cipher), Some(key), None)?;
+        if base_nonce.len() != cipher.iv_length() {
+            self.set_iv_length(base_nonce.len())?;
+        }
 
-        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
-            .unwrap();
-        ctx.set_padding(false);
+        let mut sealed = Vec::with_capacity(records.len());
+        for (record, nonce) in records.iter().zip(&nonces) {
+            self.encrypt_init(None, None, Some(nonce))?;
+            if !aad.is_empty() {
+                self.cipher_update(aad, None)?;
+            }
+            let mut ciphertext = vec![];
+            self.cipher_update_vec(record, &mut ciphertext)?;
+            self.cipher_final_vec(&mut ciphertext)?;
+            sealed.push((ciphertext, self.tag_vec()?));
+        }
 
-        let mut buf = vec![];
-        ctx.cipher_update_vec(&pt, &mut buf).unwrap();
-        ctx.cipher_final_vec(&mut buf).unwrap();
+        Ok(sealed)
+    }
 
-        assert_eq!(buf, ct);
+    /// Encrypts a packet with an authenticated cipher.
+    ///
+    /// `header` is authenticated as additional data, `body` is encrypted in place, and the authentication tag is
+    /// written to `tag`. The context must already be initialized for encryption with a key and nonce.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context's cipher is not an AEAD cipher with a block size of 1, such as AES-GCM.
+    pub fn seal_packet(
+        &mut self,
+        header: &[u8],
+        body: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), ErrorStack> {
+        self.assert_packet_cipher();
 
-        ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
-            .unwrap();
-        ctx.set_padding(false);
+        self.cipher_update(header, None)?;
+        let len = body.len();
+        self.cipher_update_inplace(body, len)?;
+        self.cipher_final(&mut [])?;
+        self.tag(tag)
+    }
 
-        let mut buf = vec![];
-        ctx.cipher_update_vec(&ct, &mut buf).unwrap();
-        ctx.cipher_final_vec(&mut buf).unwrap();
+    /// Decrypts a packet sealed by [`Self::seal_packet`].
+    ///
+    /// `header` is authenticated as additional data and `body` is decrypted in place. An error is returned if `tag`
+    /// does not match, in which case the contents of `body` must not be used. The context must already be
+    /// initialized for decryption with a key and nonce.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context's cipher is not an AEAD cipher with a block size of 1, such as AES-GCM.
+    pub fn open_packet(
+        &mut self,
+        header: &[u8],
+        body: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), ErrorStack> {
+        self.assert_packet_cipher();
 
-        assert_eq!(buf, pt);
+        self.set_tag(tag)?;
+        self.cipher_update(header, None)?;
+        let len = body.len();
+        self.cipher_update_inplace(body, len)?;
+        self.cipher_final(&mut [])?;
+        Ok(())
     }
 
-    #[test]
-    #[cfg(ossl300)]
-    fn fetched_aes_128_cbc() {
-        let cipher = Cipher::fetch(None, "AES-128-CBC", None).unwrap();
-        aes_128_cbc(&cipher);
+    /// Decrypts and verifies a message with an authenticated cipher, and checks that its plaintext begins with
+    /// `expected_prefix`.
+    ///
+    /// The context must already be initialized for decryption with a key and nonce. `aad` is authenticated as
+    /// additional data and `ciphertext` is decrypted and verified against `tag`. Only if the tag is valid, the
+    /// plaintext is checked to begin with `expected_prefix`, such as a magic number or version marker, in constant
+    /// time. If either check fails an error is returned and the plaintext is discarded.
+    pub fn open_with_expected(
+        &mut self,
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+        expected_prefix: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        self.set_tag(tag)?;
+        if !aad.is_empty() {
+            self.cipher_update(aad, None)?;
+        }
+        let mut plaintext = vec![];
+        self.cipher_update_vec(ciphertext, &mut plaintext)?;
+        self.cipher_final_vec(&mut plaintext)?;
+
+        if plaintext.len() < expected_prefix.len()
+            || !memcmp::eq(&plaintext[..expected_prefix.len()], expected_prefix)
+        {
+            return Err(ErrorStack::internal_error(
+                "plaintext does not begin with the expected prefix",
+            ));
+        }
+
+        Ok(plaintext)
     }
 
-    #[test]
-    fn default_aes_128_cbc() {
-        let cipher = Cipher::aes_128_cbc();
-        aes_128_cbc(cipher);
+    /// Encrypts `plaintext` with an authenticated cipher in one call, returning the ciphertext.
+    ///
+    /// The context must already be initialized for encryption with a key and nonce. `aad` is authenticated as
+    /// additional data, and the authentication tag is written to `tag` after the context has been finalized, so it
+    /// cannot be forgotten. In CCM mode, the total length of the plaintext is set automatically.
+    ///
+    /// Returns an error if the context is not initialized for encryption with an authenticated cipher, or if
+    /// payload has already been passed to it.
+    pub fn seal(
+        &mut self,
+        aad: &[u8],
+        plaintext: &[u8],
+        tag: &mut [u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        self.check_aead_op(true)?;
+
+        if self.cipher_mode() == CipherMode::Ccm {
+            self.set_data_len(plaintext.len())?;
+        }
+        self.set_aad(aad)?;
+        let mut ciphertext = vec![];
+        self.cipher_update_vec(plaintext, &mut ciphertext)?;
+        self.cipher_final_vec(&mut ciphertext)?;
+        self.tag(tag)?;
+        Ok(ciphertext)
+    }
+
+    /// Decrypts and verifies a message sealed by [`Self::seal`], returning the plaintext.
+    ///
+    /// The context must already be initialized for decryption with a key and nonce. `tag` is set on the context
+    /// before `aad` and `ciphertext` are processed, and an error is returned if it does not match, in which case no
+    /// plaintext is returned.
+    ///
+    /// Returns an error if the context is not initialized for decryption with an authenticated cipher, or if
+    /// payload has already been passed to it.
+    pub fn open(
+        &mut self,
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8],
+    ) -> Result<Vec<u8>, ErrorStack> {
+        self.check_aead_op(false)?;
+
+        self.set_tag(tag)?;
+        let ccm = self.cipher_mode() == CipherMode::Ccm;
+        if ccm {
+            self.set_data_len(ciphertext.len())?;
+        }
+        self.set_aad(aad)?;
+        let mut plaintext = vec![];
+        self.cipher_update_vec(ciphertext, &mut plaintext)?;
+        // CCM verifies the tag as part of the update.
+        if !ccm {
+            self.cipher_final_vec(&mut plaintext)?;
+        }
+        Ok(plaintext)
+    }
+
+    fn check_aead_op(&self, encrypting: bool) -> Result<(), ErrorStack> {
+        if !self.is_aead() {
+            return Err(ErrorStack::internal_error(
+                "cipher is not an authenticated cipher",
+            ));
+        }
+        if self.is_encrypting() != encrypting {
+            return Err(ErrorStack::internal_error(if encrypting {
+                "context is not initialized for encryption"
+            } else {
+                "context is not initialized for decryption"
+            }));
+        }
+        Ok(())
+    }
+
+    fn assert_packet_cipher(&self) {
+        self.assert_cipher();
+
+        unsafe {
+            let cipher = EVP_CIPHER_CTX_get0_cipher(self.as_ptr());
+            assert!(ffi::EVP_CIPHER_flags(cipher) & ffi::EVP_CIPH_FLAG_AEAD_CIPHER != 0);
+        }
+        assert_eq!(self.block_size(), 1);
+    }
+
+    /// Encrypts `body` and authenticates `header` separately with an authenticated cipher, returning the ciphertext
+    /// of the body, the tag over the header, and the tag over the body.
+    ///
+    /// The context must already be initialized for encryption with a key and nonce `N`. Two independent passes are
+    /// made, as if with two contexts sharing the key: the first authenticates `header` as additional data under `N`,
+    /// and the second encrypts `body` with no additional data under `N + 1`, interpreting the nonce as a big-endian
+    /// integer. The header can therefore be verified by a separate decryption pass under `N` with `header` as
+    /// additional data and no ciphertext, without decrypting the body.
+    ///
+    /// Since both `N` and `N + 1` are used, the caller must advance its nonce by two after every call, for example
+    /// by continuing with `N + 2`, and an error is returned if `N + 1` would wrap around. Reusing a nonce with the
+    /// same key breaks the security guarantees of ciphers such as AES-GCM.
+    ///
+    /// Both tags are 16 bytes long, which is the default for AES-GCM, AES-OCB and ChaCha20-Poly1305. Ciphers with
+    /// a different tag length, such as AES-CCM, are not supported.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context's cipher is not an AEAD cipher with a 16 byte tag, or if the context is not
+    /// initialized for encryption.
+    #[corresponds(EVP_CIPHER_CTX_get_original_iv)]
+    #[cfg(ossl300)]
+    #[allow(clippy::type_complexity)]
+    pub fn seal_dual(
+        &mut self,
+        header: &[u8],
+        body: &[u8],
+    ) -> Result<(Vec<u8>, [u8; 16], [u8; 16]), ErrorStack> {
+        self.assert_cipher();
+        assert!(self.is_aead(), "cipher is not an authenticated cipher");
+        assert!(
+            self.is_encrypting(),
+            "context is not initialized for encryption"
+        );
+        assert_eq!(self.tag_length(), 16, "tag length is not 16 bytes");
+
+        let mut nonce = vec![0; self.iv_length()];
+        unsafe {
+            cvt(ffi::EVP_CIPHER_CTX_get_original_iv(
+                self.as_ptr(),
+                nonce.as_mut_ptr() as *mut c_void,
+                nonce.len(),
+            ))?;
+        }
+        if !increment_be(&mut nonce) {
+            return Err(ErrorStack::internal_error("body nonce would wrap around"));
+        }
+
+        self.cipher_update(header, None)?;
+        self.cipher_final_vec(&mut vec![])?;
+        let mut header_tag = [0; 16];
+        self.tag(&mut header_tag)?;
+
+        self.encrypt_init(None, None, Some(&nonce))?;
+        let mut ciphertext = vec![];
+        self.cipher_update_vec(body, &mut ciphertext)?;
+        self.cipher_final_vec(&mut ciphertext)?;
+        let mut body_tag = [0; 16];
+        self.tag(&mut body_tag)?;
+
+        Ok((ciphertext, header_tag, body_tag))
+    }
+
+    /// Records the position of an ongoing CFB, OFB or CTR mode operation, so that it can be resumed later with
+    /// [`CipherCtx::restore`].
+    ///
+    /// The checkpoint consists of the cipher, the direction of the operation, the current IV and the offset into
+    /// the current block. It can be persisted with [`CipherCheckpoint::to_bytes`], for example to resume encrypting
+    /// a large file after a process restart. The key is not included, since it cannot be read back from the
+    /// context, and must be stored separately.
+    ///
+    /// Only the stream-like CFB, OFB and CTR modes are supported, and an error is returned for other modes.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    #[corresponds(EVP_CIPHER_CTX_get_updated_iv)]
+    #[cfg(ossl300)]
+    pub fn checkpoint(&self) -> Result<CipherCheckpoint, ErrorStack> {
+        self.assert_cipher();
+
+        unsafe {
+            let cipher = EVP_CIPHER_CTX_get0_cipher(self.as_ptr());
+            match ffi::EVP_CIPHER_flags(cipher) & ffi::EVP_CIPH_MODE {
+                ffi::EVP_CIPH_CFB_MODE | ffi::EVP_CIPH_OFB_MODE | ffi::EVP_CIPH_CTR_MODE => {}
+                _ => {
+                    return Err(ErrorStack::internal_error(
+                        "only CFB, OFB and CTR mode operations can be checkpointed",
+                    ))
+                }
+            }
+
+            let mut iv = vec![0; self.iv_length()];
+            cvt(ffi::EVP_CIPHER_CTX_get_updated_iv(
+                self.as_ptr(),
+                iv.as_mut_ptr() as *mut c_void,
+                iv.len(),
+            ))?;
+            let num = cvt_n(ffi::EVP_CIPHER_CTX_get_num(self.as_ptr()))?;
+
+            Ok(CipherCheckpoint {
+                nid: Nid::from_raw(ffi::EVP_CIPHER_nid(cipher)),
+                encrypting: ffi::EVP_CIPHER_CTX_encrypting(self.as_ptr()) != 0,
+                iv,
+                num: num as u8,
+            })
+        }
+    }
+
+    /// Derives a synthetic nonce for `message` as HMAC-SHA256 under `key`, truncated to the IV length of the context.
+    ///
+    /// This is a building block for deterministic authenticated encryption with ciphers such as AES-GCM when
+    /// AES-GCM-SIV is not available: the nonce is derived from the message, so it only repeats if the message does,
+    /// and must be transmitted along with the ciphertext. `key` must be independent of the encryption key.
+    ///
+    /// This is weaker than a true SIV construction. Identical messages produce identical ciphertexts, revealing
+    /// their equality, the nonce does not cover any additional authenticated data unless it is included in
+    /// `message`, and nonces are truncated HMAC outputs, so the usual birthday bounds of random nonces apply to the
+    /// number of distinct messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher, or if its IV length is longer than 32 bytes.
+    pub fn derive_nonce(&self, key: &[u8], message: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        let iv_len = self.iv_length();
+        assert!(iv_len <= 32);
+
+        let key = PKey::hmac(key)?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+        signer.update(message)?;
+        let mut nonce = signer.sign_to_vec()?;
+        nonce.truncate(iv_len);
+
+        Ok(nonce)
+    }
+
+    fn tag_vec(&self) -> Result<Vec<u8>, ErrorStack> {
+        #[cfg(ossl300)]
+        let mut tag = vec![0; self.tag_length()];
+        #[cfg(not(ossl300))]
+        let mut tag = vec![0; 16];
+        self.tag(&mut tag)?;
+        Ok(tag)
+    }
+}
+
+/// A circular buffer which [`CipherCtxRef::cipher_update_ring`] can write into.
+pub trait RingBuffer {
+    /// Returns the writable regions of the buffer, in order.
+    ///
+    /// The second region is used once the first one is full, and is typically the space at the start of the
+    /// buffer when the free space wraps around its end. It may be empty.
+    fn writable(&mut self) -> (&mut [u8], &mut [u8]);
+
+    /// Marks the first `len` bytes of the writable regions as filled.
+    fn commit(&mut self, len: usize);
+}
+
+/// An encryption context which has been fed its additional authenticated data.
+///
+/// This is created by [`CipherCtxRef::authenticate`].
+pub struct Authenticated<'a> {
+    ctx: &'a mut CipherCtxRef,
+}
+
+impl Authenticated<'_> {
+    /// Feeds more additional authenticated data into the context.
+    pub fn authenticate(self, header: &[u8]) -> Result<Self, ErrorStack> {
+        self.ctx.cipher_update(header, None)?;
+        Ok(self)
+    }
+
+    /// Encrypts `body` and finalizes the context, returning the ciphertext and authentication tag.
+    ///
+    /// On OpenSSL versions before 3.0.0, tags are assumed to be 16 bytes long.
+    pub fn encrypt(self, body: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+        let mut ciphertext = vec![];
+        self.ctx.cipher_update_vec(body, &mut ciphertext)?;
+        self.ctx.cipher_final_vec(&mut ciphertext)?;
+        Ok((ciphertext, self.ctx.tag_vec()?))
+    }
+
+    /// Finalizes the context without encrypting anything, returning the authentication tag.
+    pub fn finish(self) -> Result<Vec<u8>, ErrorStack> {
+        self.encrypt(&[]).map(|(_, tag)| tag)
+    }
+}
+
+fn cipher_is_aead(cipher: &CipherRef) -> bool {
+    unsafe { ffi::EVP_CIPHER_flags(cipher.as_ptr()) & ffi::EVP_CIPH_FLAG_AEAD_CIPHER != 0 }
+}
+
+fn check_ccm(cipher: &CipherRef, nonce: &[u8]) -> Result<(), ErrorStack> {
+    if unsafe { ffi::EVP_CIPHER_flags(cipher.as_ptr()) } & ffi::EVP_CIPH_MODE
+        != ffi::EVP_CIPH_CCM_MODE
+    {
+        return Err(ErrorStack::internal_error(
+            "cipher is not a CCM mode cipher",
+        ));
+    }
+    if nonce.len() < 7 || nonce.len() > 13 {
+        return Err(ErrorStack::internal_error(&format!(
+            "CCM nonce must be 7 to 13 bytes, got {}",
+            nonce.len()
+        )));
+    }
+    Ok(())
+}
+
+// Decrements `buf` as a big-endian integer, wrapping around at zero.
+#[cfg(ossl300)]
+fn decrement_be(buf: &mut [u8]) {
+    for b in buf.iter_mut().rev() {
+        *b = b.wrapping_sub(1);
+        if *b != 0xff {
+            return;
+        }
+    }
+}
+
+// Increments `buf` as a big-endian integer, returning false if it wrapped around to zero.
+fn increment_be(buf: &mut [u8]) -> bool {
+    for b in buf.iter_mut().rev() {
+        *b = b.wrapping_add(1);
+        if *b != 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// The position of a CFB, OFB or CTR mode operation.
+///
+/// This is created by [`CipherCtxRef::checkpoint`], and resumed with [`CipherCtx::restore`].
+///
+/// Requires OpenSSL 3.0.0 or newer.
+#[cfg(ossl300)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CipherCheckpoint {
+    nid: Nid,
+    encrypting: bool,
+    iv: Vec<u8>,
+    num: u8,
+}
+
+#[cfg(ossl300)]
+impl CipherCheckpoint {
+    const VERSION: u8 = 1;
+
+    /// Serializes the checkpoint.
+    ///
+    /// The encoding is specific to this crate. It does not contain any key material.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![Self::VERSION];
+        out.extend_from_slice(&self.nid.as_raw().to_be_bytes());
+        out.push(self.encrypting as u8);
+        out.push(self.num);
+        out.push(self.iv.len() as u8);
+        out.extend_from_slice(&self.iv);
+        out
+    }
+
+    /// Deserializes a checkpoint produced by [`CipherCheckpoint::to_bytes`].
+    ///
+    /// An error is returned unless the checkpoint names a CFB, OFB or CTR mode cipher, carries an IV of that
+    /// cipher's IV length, and has an offset smaller than it.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CipherCheckpoint, ErrorStack> {
+        if bytes.len() < 8
+            || bytes[0] != Self::VERSION
+            || bytes[5] > 1
+            || bytes.len() != 8 + bytes[7] as usize
+        {
+            return Err(ErrorStack::internal_error("invalid cipher checkpoint"));
+        }
+
+        let checkpoint = CipherCheckpoint {
+            nid: Nid::from_raw(c_int::from_be_bytes([
+                bytes[1], bytes[2], bytes[3], bytes[4],
+            ])),
+            encrypting: bytes[5] == 1,
+            num: bytes[6],
+            iv: bytes[8..].to_vec(),
+        };
+
+        let cipher = Cipher::from_nid(checkpoint.nid)
+            .ok_or_else(|| ErrorStack::internal_error("unknown checkpoint cipher"))?;
+        let mode = unsafe { ffi::EVP_CIPHER_flags(cipher.as_ptr()) } & ffi::EVP_CIPH_MODE;
+        // The offset is into the current block of keystream, which is as long as the IV in these modes.
+        if !matches!(
+            mode,
+            ffi::EVP_CIPH_CFB_MODE | ffi::EVP_CIPH_OFB_MODE | ffi::EVP_CIPH_CTR_MODE
+        ) || checkpoint.iv.len() != cipher.iv_length()
+            || checkpoint.num as usize >= cipher.iv_length()
+        {
+            return Err(ErrorStack::internal_error("invalid cipher checkpoint"));
+        }
+
+        Ok(checkpoint)
+    }
+}
+
+/// The mode of operation of a cipher.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CipherMode {
+    /// Electronic codebook mode.
+    Ecb,
+    /// Cipher block chaining mode.
+    Cbc,
+    /// Cipher feedback mode.
+    Cfb,
+    /// Output feedback mode.
+    Ofb,
+    /// Counter mode.
+    Ctr,
+    /// Galois/counter mode.
+    Gcm,
+    /// Counter with CBC-MAC mode.
+    Ccm,
+    /// XEX-based tweaked-codebook mode with ciphertext stealing.
+    Xts,
+    /// Key wrap mode.
+    Wrap,
+    /// Offset codebook mode.
+    Ocb,
+    /// A stream cipher, such as ChaCha20 or RC4.
+    Stream,
+    /// A mode not covered by the other variants, such as SIV.
+    Other,
+}
+
+/// An error returned by [`CipherCtxRef::try_cipher_update`].
+#[derive(Debug)]
+pub enum CipherError {
+    /// The output buffer is too small for the output the update may produce.
+    OutputTooSmall {
+        /// The minimum length of the output buffer.
+        needed: usize,
+        /// The length of the output buffer provided.
+        got: usize,
+    },
+    /// OpenSSL reported an error.
+    ErrorStack(ErrorStack),
+}
+
+impl fmt::Display for CipherError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CipherError::OutputTooSmall { needed, got } => write!(
+                fmt,
+                "output buffer too small: {} bytes needed, {} provided",
+                needed, got
+            ),
+            CipherError::ErrorStack(ref e) => write!(fmt, "{}", e),
+        }
+    }
+}
+
+impl error::Error for CipherError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CipherError::OutputTooSmall { .. } => None,
+            CipherError::ErrorStack(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<ErrorStack> for CipherError {
+    fn from(e: ErrorStack) -> CipherError {
+        CipherError::ErrorStack(e)
+    }
+}
+
+/// A cipher context with additional safeguards and bookkeeping implemented in this crate.
+///
+/// The session owns a [`CipherCtx`], and its methods for initializing the context and processing data track the
+/// operation as they go. This provides features which OpenSSL has no support for: length-hiding padding, a limit on
+/// the amount of data encrypted with one key, detection of reused AEAD nonces, enforcement that AAD precedes the
+/// payload, and reporting of the input buffered in a partial block and of the block padding removed.
+///
+/// Other settings, such as disabling block padding or setting the expected authentication tag, are made on the
+/// context returned by [`CipherSession::ctx_mut`]. Initializing the context or processing data through it directly
+/// bypasses the session's bookkeeping.
+pub struct CipherSession {
+    ctx: CipherCtx,
+    encrypting: bool,
+    // Whether payload has been processed since the context was last initialized.
+    payload_started: bool,
+    // The multiple to pad plaintext to, or 0 if length-hiding padding is disabled.
+    pad_to: usize,
+    // The length of the plaintext processed so far, modulo `pad_to`.
+    pad_offset: usize,
+    // Decrypted plaintext which is withheld because it may be padding.
+    held: Vec<u8>,
+    // The number of bytes of payload buffered by the cipher in a partial block.
+    buffered: usize,
+    // The number of block padding bytes removed by the last finalization, if any.
+    removed_padding: Option<usize>,
+    // The maximum number of bytes to encrypt with one key, if limited.
+    usage_limit: Option<u64>,
+    // The number of bytes encrypted with the current key.
+    usage: u64,
+    // The tracker consulted for AEAD nonces, if any.
+    nonce_tracker: Option<Arc<Mutex<NonceTracker>>>,
+    // The fingerprint of the current key under the tracker's salt, if known.
+    key_id: Option<[u8; 32]>,
+    // Whether nonce reuse detected by the tracker panics rather than returning an error.
+    panic_on_nonce_reuse: bool,
+}
+
+impl CipherSession {
+    /// Creates a new session with an uninitialized context.
+    #[corresponds(EVP_CIPHER_CTX_new)]
+    pub fn new() -> Result<CipherSession, ErrorStack> {
+        Ok(CipherSession {
+            ctx: CipherCtx::new()?,
+            encrypting: false,
+            payload_started: false,
+            pad_to: 0,
+            pad_offset: 0,
+            held: vec![],
+            buffered: 0,
+            removed_padding: None,
+            usage_limit: None,
+            usage: 0,
+            nonce_tracker: None,
+            key_id: None,
+            panic_on_nonce_reuse: false,
+        })
+    }
+
+    /// Returns a shared reference to the underlying cipher context.
+    pub fn ctx(&self) -> &CipherCtxRef {
+        &self.ctx
+    }
+
+    /// Returns a mutable reference to the underlying cipher context.
+    pub fn ctx_mut(&mut self) -> &mut CipherCtxRef {
+        &mut self.ctx
+    }
+
+    /// Initializes the context for encryption.
+    ///
+    /// This is [`CipherCtxRef::encrypt_init`], which additionally resets the usage counter if a key is set, and
+    /// checks the nonce against the attached [`NonceTracker`], if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher, the IV buffer is smaller than the IV size
+    /// of the cipher, or if a key or IV is provided before a cipher.
+    #[corresponds(EVP_EncryptInit_ex)]
+    pub fn encrypt_init(
+        &mut self,
+        type_: Option<&CipherRef>,
+        key: Option<&[u8]>,
+        iv: Option<&[u8]>,
+    ) -> Result<(), ErrorStack> {
+        self.init(type_, key, iv, true)
+    }
+
+    /// Initializes the context for decryption.
+    ///
+    /// This is [`CipherCtxRef::decrypt_init`], which additionally resets the usage counter if a key is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher, the IV buffer is smaller than the IV size
+    /// of the cipher, or if a key or IV is provided before a cipher.
+    #[corresponds(EVP_DecryptInit_ex)]
+    pub fn decrypt_init(
+        &mut self,
+        type_: Option<&CipherRef>,
+        key: Option<&[u8]>,
+        iv: Option<&[u8]>,
+    ) -> Result<(), ErrorStack> {
+        self.init(type_, key, iv, false)
+    }
+
+    fn init(
+        &mut self,
+        type_: Option<&CipherRef>,
+        key: Option<&[u8]>,
+        iv: Option<&[u8]>,
+        encrypting: bool,
+    ) -> Result<(), ErrorStack> {
+        let tracker = self.nonce_tracker.clone();
+        // The tracker is never left inconsistent, so it remains usable if another user of it panicked.
+        let mut tracker = tracker
+            .as_ref()
+            .map(|tracker| tracker.lock().unwrap_or_else(PoisonError::into_inner));
+        let (key_id, entry) = match &tracker {
+            Some(tracker) => self.check_nonce(tracker, type_, key, iv, encrypting)?,
+            None => (None, None),
+        };
+        let reused = match (&tracker, &entry) {
+            (Some(tracker), Some(entry)) => tracker.contains(entry),
+            _ => false,
+        };
+        if reused {
+            // release the lock first so the tracker remains usable by the rest of the context's lineage
+            drop(tracker);
+            if self.panic_on_nonce_reuse {
+                panic!("nonce has already been used with this key");
+            }
+            return Err(ErrorStack::internal_error(
+                "nonce has already been used with this key",
+            ));
+        }
+
+        if encrypting {
+            self.ctx.encrypt_init(type_, key, iv)?;
+        } else {
+            self.ctx.decrypt_init(type_, key, iv)?;
+        }
+
+        // The nonce is only recorded once it has actually been used.
+        if let (Some(tracker), Some(entry)) = (&mut tracker, entry) {
+            tracker.insert(entry);
+        }
+        drop(tracker);
+
+        self.encrypting = encrypting;
+        self.start_operation();
+        if key.is_some() {
+            self.usage = 0;
+        }
+        if self.nonce_tracker.is_some() && (key.is_some() || type_.is_some()) {
+            self.key_id = key_id;
+        }
+
+        Ok(())
+    }
+
+    // Determines the fingerprint of the key which will be in effect after initialization, and, for an AEAD
+    // encryption, the tracker entry of the nonce.
+    #[allow(clippy::type_complexity)]
+    fn check_nonce(
+        &self,
+        tracker: &NonceTracker,
+        type_: Option<&CipherRef>,
+        key: Option<&[u8]>,
+        iv: Option<&[u8]>,
+        encrypting: bool,
+    ) -> Result<(Option<[u8; 32]>, Option<[u8; 32]>), ErrorStack> {
+        let key_id = match key {
+            Some(key) => Some(tracker.key_id(key)?),
+            None if type_.is_some() => None,
+            None => self.key_id,
+        };
+
+        let iv = match iv {
+            Some(iv) if encrypting => iv,
+            _ => return Ok((key_id, None)),
+        };
+        let cipher = match type_ {
+            Some(cipher) => cipher.as_ptr(),
+            None => unsafe { EVP_CIPHER_CTX_get0_cipher(self.ctx.as_ptr()) },
+        };
+        if cipher.is_null()
+            || unsafe { ffi::EVP_CIPHER_flags(cipher) } & ffi::EVP_CIPH_FLAG_AEAD_CIPHER == 0
+        {
+            return Ok((key_id, None));
+        }
+
+        let key_id = key_id.ok_or_else(|| {
+            ErrorStack::internal_error("the key must be set after attaching a nonce tracker")
+        })?;
+        let iv_len = type_.map_or_else(|| self.ctx.iv_length(), |c| c.iv_length());
+        let entry = tracker.entry(&key_id, &iv[..iv_len])?;
+
+        Ok((Some(key_id), Some(entry)))
+    }
+
+    fn start_operation(&mut self) {
+        self.payload_started = false;
+        self.pad_offset = 0;
+        self.held.clear();
+        self.buffered = 0;
+        self.removed_padding = None;
+    }
+
+    /// Clears the cipher, key, and IV of the context so that it can be initialized again.
+    ///
+    /// This is [`CipherCtxRef::reset`]. Settings of the session, such as [`Self::set_pad_to`],
+    /// [`Self::set_usage_limit`] and an attached [`NonceTracker`], are retained.
+    #[corresponds(EVP_CIPHER_CTX_reset)]
+    pub fn reset(&mut self) -> Result<(), ErrorStack> {
+        self.ctx.reset()?;
+        self.start_operation();
+        self.usage = 0;
+        self.key_id = None;
+
+        Ok(())
+    }
+
+    /// Creates a new session which duplicates the current state of this one.
+    ///
+    /// The context is duplicated as by [`CipherCtxRef::try_clone`]. Settings of the session are copied as well, but
+    /// the two sessions count usage separately afterwards. An attached [`NonceTracker`] is shared.
+    #[corresponds(EVP_CIPHER_CTX_copy)]
+    pub fn try_clone(&self) -> Result<CipherSession, ErrorStack> {
+        Ok(CipherSession {
+            ctx: self.ctx.try_clone()?,
+            encrypting: self.encrypting,
+            payload_started: self.payload_started,
+            pad_to: self.pad_to,
+            pad_offset: self.pad_offset,
+            held: self.held.clone(),
+            buffered: self.buffered,
+            removed_padding: self.removed_padding,
+            usage_limit: self.usage_limit,
+            usage: self.usage,
+            nonce_tracker: self.nonce_tracker.clone(),
+            key_id: self.key_id,
+            panic_on_nonce_reuse: self.panic_on_nonce_reuse,
+        })
+    }
+
+    /// Enables length-hiding padding of the plaintext to a multiple of `multiple` bytes.
+    ///
+    /// When encrypting, [`Self::cipher_final`] appends a `0x80` byte followed by as many zero bytes as needed to
+    /// extend the plaintext to the next multiple of `multiple` bytes, as in ISO/IEC 7816-4. At least one byte of
+    /// padding is always added, so a plaintext which already is a multiple of `multiple` bytes long is extended by a
+    /// full `multiple` bytes. This is independent of, and applied before, the block padding of the cipher. When
+    /// decrypting, up to `multiple` bytes of plaintext are withheld by [`Self::cipher_update`], and
+    /// [`Self::cipher_final`] checks and removes the padding, returning an error if it is invalid.
+    ///
+    /// This hides the exact length of the message, to make traffic analysis harder. The setting applies to
+    /// subsequent operations until changed. A `multiple` of 0 or 1 disables the padding, which is the default.
+    ///
+    /// Enabling the padding increases the output buffer size required by [`Self::cipher_final`].
+    pub fn set_pad_to(&mut self, multiple: usize) {
+        self.pad_to = if multiple > 1 { multiple } else { 0 };
+        self.pad_offset = 0;
+        self.held.clear();
+    }
+
+    /// Limits the number of bytes which may be encrypted with a single key.
+    ///
+    /// The session counts the plaintext bytes passed to [`Self::cipher_update`] and its variants when encrypting,
+    /// and returns an error without processing the input once a call would take the total beyond `max_bytes`. This
+    /// enforces data limits after which a key must be replaced, such as those recommended for AES-GCM. The counter
+    /// is reset whenever a new key is set by initializing the context, but the limit remains in effect.
+    pub fn set_usage_limit(&mut self, max_bytes: u64) {
+        self.usage_limit = Some(max_bytes);
+    }
+
+    /// Attaches a [`NonceTracker`] which is consulted whenever the context is initialized for encryption with an
+    /// AEAD cipher.
+    ///
+    /// Initialization fails without touching the context if the nonce has already been used with the same key within
+    /// the tracker's window. The tracker only learns about keys supplied after it is attached, so it must be attached
+    /// before the key is set. It can be shared between all sessions using the same keys, so that a nonce reused by
+    /// another session is also detected.
+    pub fn set_nonce_tracker(&mut self, tracker: Arc<Mutex<NonceTracker>>) {
+        self.nonce_tracker = Some(tracker);
+        self.key_id = None;
+    }
+
+    /// Enables a debug-build check which panics if the context is initialized for encryption with an AEAD cipher,
+    /// such as AES-GCM or ChaCha20-Poly1305, using a (key, nonce) pair it has seen before.
+    ///
+    /// This is a development aid to catch nonce reuse early, and does nothing in release builds. Unless a
+    /// [`NonceTracker`] is already attached, an unbounded one is attached to record every pair, so the check covers
+    /// the whole lifetime of the session and of any clones made with [`Self::try_clone`], at the cost of memory
+    /// growing with each encryption. Like [`Self::set_nonce_tracker`], it must be enabled before the key is set.
+    pub fn enable_nonce_reuse_check(&mut self) -> Result<(), ErrorStack> {
+        #[cfg(debug_assertions)]
+        {
+            if self.nonce_tracker.is_none() {
+                let tracker = NonceTracker::new(usize::MAX)?;
+                self.set_nonce_tracker(Arc::new(Mutex::new(tracker)));
+            }
+            self.panic_on_nonce_reuse = true;
+        }
+        Ok(())
+    }
+
+    // Counts `len` bytes against the usage limit, returning an error if it would be exceeded.
+    fn consume_usage(&mut self, len: usize) -> Result<(), ErrorStack> {
+        let limit = match self.usage_limit {
+            Some(limit) if self.encrypting => limit,
+            _ => return Ok(()),
+        };
+
+        match self.usage.checked_add(len as u64) {
+            Some(usage) if usage <= limit => {
+                self.usage = usage;
+                Ok(())
+            }
+            _ => Err(ErrorStack::internal_error(&format!(
+                "usage limit of {} bytes for this key exceeded",
+                limit
+            ))),
+        }
+    }
+
+    /// Feeds `aad` into the context as additional authenticated data.
+    ///
+    /// This is [`CipherCtxRef::set_aad`], except that an error is returned if payload has already been passed to
+    /// [`Self::cipher_update`] since the context was last initialized.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn set_aad(&mut self, aad: &[u8]) -> Result<(), ErrorStack> {
+        self.check_aad_order()?;
+        self.ctx.set_aad(aad)
+    }
+
+    /// Reads additional authenticated data (AAD) from `reader` until EOF and feeds it into the context.
+    ///
+    /// This avoids holding large AAD in memory. Returns the number of bytes read. Errors of the reader are returned as
+    /// they are, and errors of OpenSSL are converted into [`io::Error`]s.
+    ///
+    /// All AAD must be provided before any payload. Some ciphers, such as ChaCha20-Poly1305, silently accept AAD
+    /// after payload data, producing a tag which will not match a conforming implementation, so an error is
+    /// returned if payload has already been passed to [`Self::cipher_update`] since the context was last
+    /// initialized.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn update_aad_stream<R>(&mut self, reader: &mut R) -> io::Result<u64>
+    where
+        R: Read,
+    {
+        self.check_aad_order()?;
+
+        let mut buf = [0; 8192];
+        let mut total = 0;
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(len) => len,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.ctx.set_aad(&buf[..len])?;
+            total += len as u64;
+        }
+    }
+
+    fn check_aad_order(&self) -> Result<(), ErrorStack> {
+        if self.payload_started {
+            return Err(ErrorStack::internal_error(
+                "AAD must be provided before any payload",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes payload data into the context.
+    ///
+    /// Returns the number of bytes written to `output`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output.len()` is less than `input.len()` plus the cipher's block size, or twice the block size for
+    /// key wrap ciphers.
+    #[corresponds(EVP_CipherUpdate)]
+    pub fn cipher_update(&mut self, input: &[u8], output: &mut [u8]) -> Result<usize, ErrorStack> {
+        self.consume_usage(input.len())?;
+
+        let outlen = self.ctx.cipher_update(input, Some(&mut *output))?;
+        self.payload_started = true;
+        self.buffered = (self.buffered + input.len()).saturating_sub(outlen);
+
+        Ok(self.pad_update(input.len(), output, outlen))
+    }
+
+    // Tracks the plaintext length for length-hiding padding, and withholds decrypted data which may be padding.
+    fn pad_update(&mut self, inlen: usize, output: &mut [u8], outlen: usize) -> usize {
+        if self.pad_to == 0 {
+            return outlen;
+        }
+
+        if self.encrypting {
+            self.pad_offset = (self.pad_offset + inlen) % self.pad_to;
+            return outlen;
+        }
+
+        self.pad_offset = (self.pad_offset + outlen) % self.pad_to;
+        self.held.extend_from_slice(&output[..outlen]);
+        let released = self.held.len().saturating_sub(self.pad_to);
+        output[..released].copy_from_slice(&self.held[..released]);
+        self.held.drain(..released);
+
+        released
+    }
+
+    /// Like [`Self::cipher_update`] except that it appends output to a [`Vec`].
+    pub fn cipher_update_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<usize, ErrorStack> {
+        let base = output.len();
+        output.resize(base + input.len() + self.ctx.update_overhead(), 0);
+        let len = self.cipher_update(input, &mut output[base..])?;
+        output.truncate(base + len);
+
+        Ok(len)
+    }
+
+    /// Finalizes the encryption or decryption process.
+    ///
+    /// Any remaining data will be written to the output buffer. Returns the number of bytes written to `output`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` is smaller than the cipher's block size, or if length-hiding padding has been enabled with
+    /// [`Self::set_pad_to`] and `output` is smaller than the padding multiple plus twice the cipher's block size.
+    #[corresponds(EVP_CipherFinal)]
+    pub fn cipher_final(&mut self, output: &mut [u8]) -> Result<usize, ErrorStack> {
+        if self.pad_to != 0 {
+            assert!(output.len() >= self.final_len_bound());
+        }
+
+        let mut padded = 0;
+        if self.pad_to != 0 && self.encrypting {
+            let mut padding = vec![0; self.pad_to - self.pad_offset];
+            padding[0] = 0x80;
+            padded = self.ctx.cipher_update(&padding, Some(&mut *output))?;
+        }
+
+        let outlen = self.ctx.cipher_final(&mut output[padded..])?;
+        self.buffered = 0;
+
+        let block_size = self.ctx.block_size();
+        self.removed_padding = if block_size > 1 && !self.encrypting && self.ctx.padding() {
+            Some(block_size - outlen)
+        } else {
+            None
+        };
+
+        if self.pad_to != 0 && !self.encrypting {
+            return self.pad_final(output, outlen);
+        }
+
+        Ok(padded + outlen)
+    }
+
+    // Checks and removes length-hiding padding from the withheld plaintext and the output of the final step.
+    fn pad_final(&mut self, output: &mut [u8], outlen: usize) -> Result<usize, ErrorStack> {
+        let offset = (self.pad_offset + outlen) % self.pad_to;
+        let mut data = mem::take(&mut self.held);
+        data.extend_from_slice(&output[..outlen]);
+
+        let len = match data.iter().rposition(|b| *b != 0) {
+            Some(pos) if offset == 0 && data[pos] == 0x80 && data.len() - pos <= self.pad_to => pos,
+            _ => return Err(ErrorStack::internal_error("invalid length-hiding padding")),
+        };
+        output[..len].copy_from_slice(&data[..len]);
+
+        Ok(len)
+    }
+
+    // The output buffer size required by `cipher_final`.
+    fn final_len_bound(&self) -> usize {
+        match self.pad_to {
+            0 => self.ctx.block_size(),
+            pad_to => pad_to + 2 * self.ctx.block_size(),
+        }
+    }
+
+    /// Like [`Self::cipher_final`] except that it appends output to a [`Vec`].
+    pub fn cipher_final_vec(&mut self, output: &mut Vec<u8>) -> Result<usize, ErrorStack> {
+        let base = output.len();
+        output.resize(base + self.final_len_bound(), 0);
+        let len = self.cipher_final(&mut output[base..])?;
+        output.truncate(base + len);
+
+        Ok(len)
+    }
+
+    /// Returns the number of bytes the call to [`Self::cipher_final`] will write, given the total length of the
+    /// input passed to [`Self::cipher_update`].
+    ///
+    /// Without length-hiding padding this matches [`CipherCtxRef::cipher_final_len`]. When padding is enabled with
+    /// [`Self::set_pad_to`], the input length determines how much of it is added. When decrypting, the maximum is
+    /// returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context has not been initialized with a cipher.
+    pub fn final_output_len(&self, total_input: usize) -> usize {
+        let block_size = self.ctx.block_size();
+        let block_padding = block_size > 1 && self.ctx.padding();
+
+        if self.pad_to == 0 {
+            return match (block_padding, self.encrypting) {
+                (false, _) => 0,
+                (true, true) => block_size,
+                (true, false) => block_size - 1,
+            };
+        }
+
+        if !self.encrypting {
+            return self.pad_to - 1 + if block_padding { block_size - 1 } else { 0 };
+        }
+
+        let padded = total_input + self.pad_to - total_input % self.pad_to;
+        let written = total_input - total_input % block_size;
+        if block_padding {
+            (padded / block_size + 1) * block_size - written
+        } else {
+            padded - written
+        }
+    }
+
+    /// Returns the number of bytes of input buffered by the context in a partial block.
+    ///
+    /// Block ciphers such as AES-CBC only produce output in whole blocks, and hold the remainder of the input until
+    /// the next call to [`Self::cipher_update`] or [`Self::cipher_final`]. When decrypting with padding, the last
+    /// complete block is held as well, as it may contain the padding. The output of a subsequent update with `n`
+    /// bytes of input is therefore at most `buffered_len() + n` rounded down to a multiple of the block size.
+    ///
+    /// Decrypted data withheld because of length-hiding padding enabled with [`Self::set_pad_to`] is not included.
+    pub fn buffered_len(&self) -> usize {
+        self.buffered
+    }
+
+    /// Returns the number of padding bytes removed by the last call to [`Self::cipher_final`].
+    ///
+    /// Returns `None` if the last finalization was not a decryption using a padded block cipher, or if the context
+    /// has not been finalized since it was last initialized.
+    pub fn removed_padding_bytes(&self) -> Option<usize> {
+        self.removed_padding
+    }
+}
+
+/// A detector for nonces reused with the same key.
+///
+/// The tracker remembers the most recent `capacity` (key, nonce) pairs passed to it through
+/// [`CipherSession::set_nonce_tracker`] or [`CipherCtxRef::encrypt_init_checked`], and reports a pair seen again
+/// within that window. It is defense in depth for systems which cannot rule out nonce reuse structurally, for example
+/// because nonces are persisted across restarts. Reuse of a pair which has already dropped out of the window is not
+/// detected.
+///
+/// Pairs are stored as exact 256-bit fingerprints rather than in a bloom filter. This costs more memory per entry,
+/// roughly 100 bytes including bookkeeping, but avoids false positives, which would otherwise make legitimate
+/// encryptions fail at a rate growing with the fill of the filter. Fingerprints are salted with a random value
+/// chosen when the tracker is created, so neither keys nor nonces are kept in memory.
+pub struct NonceTracker {
+    salt: [u8; 32],
+    capacity: usize,
+    order: VecDeque<[u8; 32]>,
+    seen: HashSet<[u8; 32]>,
+}
+
+impl NonceTracker {
+    /// Creates a tracker which remembers up to `capacity` (key, nonce) pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Result<NonceTracker, ErrorStack> {
+        assert!(capacity > 0);
+
+        let mut salt = [0; 32];
+        rand_bytes(&mut salt)?;
+        Ok(NonceTracker {
+            salt,
+            capacity,
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Returns the maximum number of pairs remembered by the tracker.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of pairs currently remembered by the tracker.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Determines if the tracker has not remembered any pairs yet.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    fn key_id(&self, key: &[u8]) -> Result<[u8; 32], ErrorStack> {
+        self.fingerprint(b"key", key)
+    }
+
+    fn entry(&self, key_id: &[u8; 32], nonce: &[u8]) -> Result<[u8; 32], ErrorStack> {
+        let mut input = key_id.to_vec();
+        input.extend_from_slice(nonce);
+        self.fingerprint(b"nonce", &input)
+    }
+
+    fn contains(&self, entry: &[u8; 32]) -> bool {
+        self.seen.contains(entry)
+    }
+
+    // Remembers the pair, forgetting the oldest one if the tracker is full.
+    fn insert(&mut self, entry: [u8; 32]) {
+        if !self.seen.insert(entry) {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            let oldest = self.order.pop_front().unwrap();
+            self.seen.remove(&oldest);
+        }
+        self.order.push_back(entry);
+    }
+
+    fn fingerprint(&self, label: &[u8], data: &[u8]) -> Result<[u8; 32], ErrorStack> {
+        let mut hasher = Hasher::new(MessageDigest::sha256())?;
+        hasher.update(&self.salt)?;
+        hasher.update(label)?;
+        hasher.update(data)?;
+        let digest = hasher.finish()?;
+
+        let mut out = [0; 32];
+        out.copy_from_slice(&digest);
+        Ok(out)
+    }
+}
+
+/// A decryption stream for authenticated ciphers which withholds plaintext until the tag is verified.
+///
+/// Decrypted data is accumulated internally and only released by [`OpenStream::finalize`] once the
+/// authentication tag has been checked, so unauthenticated plaintext can never be acted upon.
+///
+/// This is intended for ciphers such as AES-GCM and ChaCha20-Poly1305 which accept their input
+/// incrementally. CCM mode requires the entire message in a single update and is not supported.
+pub struct OpenStream {
+    ctx: CipherCtx,
+    plaintext: Vec<u8>,
+}
+
+impl OpenStream {
+    /// Creates a new stream decrypting with the given AEAD cipher, key, and IV.
+    ///
+    /// The IV length of the cipher is adjusted if `iv` is not of the cipher's default IV length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher.
+    pub fn new(cipher: &CipherRef, key: &[u8], iv: &[u8]) -> Result<OpenStream, ErrorStack> {
+        let mut ctx = CipherCtx::new()?;
+        ctx.decrypt_init(Some(cipher), None, None)?;
+        if iv.len() != cipher.iv_length() {
+            ctx.set_iv_length(iv.len())?;
+        }
+        ctx.decrypt_init(None, Some(key), Some(iv))?;
+
+        Ok(OpenStream {
+            ctx,
+            plaintext: vec![],
+        })
+    }
+
+    /// Feeds additional authenticated data into the stream.
+    ///
+    /// All AAD must be provided before the first call to [`OpenStream::update`].
+    pub fn aad_update(&mut self, aad: &[u8]) -> Result<(), ErrorStack> {
+        self.ctx.cipher_update(aad, None)?;
+        Ok(())
+    }
+
+    /// Decrypts a chunk of ciphertext into the stream's internal buffer.
+    pub fn update(&mut self, ciphertext: &[u8]) -> Result<(), ErrorStack> {
+        self.ctx
+            .cipher_update_vec(ciphertext, &mut self.plaintext)?;
+        Ok(())
+    }
+
+    /// Verifies the authentication tag and returns the plaintext.
+    ///
+    /// If verification fails, the buffered plaintext is discarded and an error is returned.
+    pub fn finalize(mut self, tag: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+        self.ctx.set_tag(tag)?;
+        self.ctx.cipher_final_vec(&mut self.plaintext)?;
+        Ok(self.plaintext)
+    }
+}
+
+/// An AES-GCM encryption session which constructs a unique IV for every message.
+///
+/// IVs are built deterministically as described in NIST SP 800-38D section 8.2.1: a 32 bit fixed field
+/// identifying the device or context, followed by a 64 bit invocation field which is incremented for every
+/// message. Once all invocation field values have been used, the generator refuses to produce more IVs rather
+/// than repeat one.
+///
+/// Distinct generators using the same key must use distinct fixed fields.
+pub struct GcmNonceGenerator {
+    ctx: CipherCtx,
+    fixed: [u8; 4],
+    invocation: u64,
+    exhausted: bool,
+}
+
+impl GcmNonceGenerator {
+    /// Creates a new generator encrypting with the given AES-GCM cipher and key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher, or if the cipher does not use a 96 bit
+    /// IV by default.
+    pub fn new(
+        cipher: &CipherRef,
+        key: &[u8],
+        fixed: [u8; 4],
+    ) -> Result<GcmNonceGenerator, ErrorStack> {
+        assert_eq!(cipher.iv_length(), 12);
+
+        let mut ctx = CipherCtx::new()?;
+        ctx.encrypt_init(Some(cipher), Some(key), None)?;
+
+        Ok(GcmNonceGenerator {
+            ctx,
+            fixed,
+            invocation: 0,
+            exhausted: false,
+        })
+    }
+
+    /// Initializes the context for a new message with the next IV, and returns that IV.
+    ///
+    /// The message can then be encrypted through [`GcmNonceGenerator::ctx_mut`].
+    ///
+    /// Returns an error once the invocation field has been exhausted.
+    #[corresponds(EVP_EncryptInit_ex)]
+    pub fn next_encrypt_init(&mut self) -> Result<[u8; 12], ErrorStack> {
+        if self.exhausted {
+            return Err(ErrorStack::internal_error("GCM invocation field exhausted"));
+        }
+
+        let mut iv = [0; 12];
+        iv[..4].copy_from_slice(&self.fixed);
+        iv[4..].copy_from_slice(&self.invocation.to_be_bytes());
+        self.ctx.encrypt_init(None, None, Some(&iv))?;
+
+        match self.invocation.checked_add(1) {
+            Some(invocation) => self.invocation = invocation,
+            None => self.exhausted = true,
+        }
+
+        Ok(iv)
+    }
+
+    /// Returns a shared reference to the underlying cipher context.
+    pub fn ctx(&self) -> &CipherCtxRef {
+        &self.ctx
+    }
+
+    /// Returns a mutable reference to the underlying cipher context.
+    pub fn ctx_mut(&mut self) -> &mut CipherCtxRef {
+        &mut self.ctx
+    }
+}
+
+/// A key shared by multiple independent authenticated encryption streams.
+///
+/// Each stream created by [`KeyedAead::new_stream`] is a [`GcmNonceGenerator`] whose 32 bit fixed field is a
+/// stream identifier assigned sequentially by this type, so the IV space is partitioned between streams: the IV of
+/// every message is the big-endian stream identifier followed by the big-endian 64 bit message counter of its
+/// stream. As long as a single `KeyedAead` creates all streams for a key, no two messages are encrypted with the
+/// same IV.
+///
+/// The copy of the key held by this type is overwritten with zeros when it is dropped.
+pub struct KeyedAead<'a> {
+    cipher: &'a CipherRef,
+    key: Vec<u8>,
+    next_stream: u32,
+    exhausted: bool,
+}
+
+impl<'a> KeyedAead<'a> {
+    /// Creates a new shared key for the given authenticated cipher, such as AES-GCM.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key buffer is smaller than the key size of the cipher, or if the cipher does not use a 96 bit
+    /// IV by default.
+    pub fn new(cipher: &'a CipherRef, key: &[u8]) -> KeyedAead<'a> {
+        assert_eq!(cipher.iv_length(), 12);
+        assert!(key.len() >= cipher.key_length());
+
+        KeyedAead {
+            cipher,
+            key: key.to_vec(),
+            next_stream: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Creates an encryption context for a new stream, with the next unused stream identifier.
+    ///
+    /// Returns an error once all 2^32 stream identifiers have been used.
+    pub fn new_stream(&mut self) -> Result<GcmNonceGenerator, ErrorStack> {
+        if self.exhausted {
+            return Err(ErrorStack::internal_error(
+                "AEAD stream identifiers exhausted",
+            ));
+        }
+
+        let stream =
+            GcmNonceGenerator::new(self.cipher, &self.key, self.next_stream.to_be_bytes())?;
+
+        match self.next_stream.checked_add(1) {
+            Some(next_stream) => self.next_stream = next_stream,
+            None => self.exhausted = true,
+        }
+
+        Ok(stream)
+    }
+
+    /// Creates a context for decrypting a message of any stream with the given IV.
+    ///
+    /// The IV is the one returned by [`GcmNonceGenerator::next_encrypt_init`] when the message was encrypted.
+    #[corresponds(EVP_DecryptInit_ex)]
+    pub fn decrypt_ctx(&self, iv: &[u8]) -> Result<CipherCtx, ErrorStack> {
+        let mut ctx = CipherCtx::new()?;
+        ctx.decrypt_init(Some(self.cipher), Some(&self.key), Some(iv))?;
+        Ok(ctx)
+    }
+}
+
+impl Drop for KeyedAead<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::OPENSSL_cleanse(self.key.as_mut_ptr() as *mut c_void, self.key.len());
+        }
+    }
+}
+
+/// A cipher context adapter which writes all output into a caller-provided scratch buffer.
+///
+/// This is created by [`CipherCtxRef::with_scratch`].
+pub struct ScratchCipher<'a> {
+    ctx: &'a mut CipherCtxRef,
+    scratch: &'a mut Vec<u8>,
+}
+
+impl ScratchCipher<'_> {
+    /// Writes data into the context, returning the output.
+    pub fn update(&mut self, input: &[u8]) -> Result<&[u8], ErrorStack> {
+        self.ctx.cipher_update_reuse(input, self.scratch)?;
+        Ok(self.scratch)
+    }
+
+    /// Finalizes the encryption or decryption process, returning the output.
+    pub fn finalize(&mut self) -> Result<&[u8], ErrorStack> {
+        self.scratch.clear();
+        self.ctx.cipher_final_vec(self.scratch)?;
+        Ok(self.scratch)
+    }
+
+    /// Reads data from `reader` until EOF, writing the processed data to `writer`.
+    ///
+    /// The context is not finalized. Returns the number of bytes read. Errors of the reader and writer are returned as
+    /// they are, and errors of OpenSSL are converted into [`io::Error`]s.
+    pub fn update_stream<R, W>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<u64>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut buf = [0; 8192];
+        let mut total = 0;
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(len) => len,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let output = self.update(&buf[..len])?;
+            writer.write_all(output)?;
+            total += len as u64;
+        }
+    }
+}
+
+/// A writer which encrypts or decrypts all data written to it with a cipher context, passing the output on to an
+/// inner writer.
+///
+/// This allows a context to be used with [`io::copy`] and other `Write`-based code. The context must already be
+/// initialized. Data is passed through [`CipherCtxRef::cipher_update`] as it is written, and [`CipherWriter::finish`]
+/// must be called once all data has been written to process any partial final block and recover the inner writer.
+/// Dropping the writer without finishing it leaves the operation incomplete.
+///
+/// # Examples
+///
+/// ```
+/// use openssl::cipher::Cipher;
+/// use openssl::cipher_ctx::{CipherCtx, CipherWriter};
+/// use std::io::{self, Write};
+///
+/// let mut ctx = CipherCtx::new().unwrap();
+/// ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+///     .unwrap();
+///
+/// let mut writer = CipherWriter::new(&mut ctx, vec![]);
+/// io::copy(&mut &b"some plaintext"[..], &mut writer).unwrap();
+/// let ciphertext = writer.finish().unwrap();
+/// assert_eq!(ciphertext.len(), 16);
+/// ```
+pub struct CipherWriter<'a, W> {
+    ctx: &'a mut CipherCtxRef,
+    writer: W,
+}
+
+impl<'a, W> CipherWriter<'a, W>
+where
+    W: Write,
+{
+    /// Creates a writer passing data through `ctx` into `writer`.
+    pub fn new(ctx: &'a mut CipherCtxRef, writer: W) -> CipherWriter<'a, W> {
+        CipherWriter { ctx, writer }
+    }
+
+    /// Returns a shared reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    ///
+    /// Writing to it directly interleaves data with the output of the context.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Finalizes the operation, writing any remaining output to the inner writer, and returns it.
+    ///
+    /// For authenticated ciphers, the tag can be retrieved from the context afterwards.
+    #[corresponds(EVP_CipherFinal)]
+    pub fn finish(mut self) -> io::Result<W> {
+        self.ctx.cipher_final_to_writer(&mut self.writer)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W> Write for CipherWriter<'_, W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.ctx.cipher_update_to_writer(buf, &mut self.writer)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A builder for a [`CipherCtx`] which is ready to encrypt or decrypt.
+///
+/// Initializing a context takes several calls which must be made in the right order: for authenticated ciphers,
+/// the tag and IV lengths must be configured after the cipher has been set but before the key, and AAD must follow
+/// the key. The builder makes the calls in that order, and checks the lengths of the key, IV and tag against the
+/// cipher first, returning an error rather than panicking if they do not match.
+///
+/// # Examples
+///
+/// ```
+/// use openssl::cipher::Cipher;
+/// use openssl::cipher_ctx::CipherCtxBuilder;
+///
+/// let key = [0; 32];
+/// let iv = [0; 12];
+/// let mut ctx = CipherCtxBuilder::new()
+///     .cipher(Cipher::aes_256_gcm())
+///     .key(&key)
+///     .iv(&iv)
+///     .aad(b"header")
+///     .build_encrypt()
+///     .unwrap();
+///
+/// let mut ciphertext = vec![];
+/// ctx.cipher_update_vec(b"body", &mut ciphertext).unwrap();
+/// ctx.cipher_final_vec(&mut ciphertext).unwrap();
+/// let mut tag = [0; 16];
+/// ctx.tag(&mut tag).unwrap();
+/// ```
+pub struct CipherCtxBuilder<'a> {
+    cipher: Option<&'a CipherRef>,
+    key: Option<&'a [u8]>,
+    iv: Option<&'a [u8]>,
+    padding: bool,
+    aad: Option<&'a [u8]>,
+    tag_len: Option<usize>,
+}
+
+impl Default for CipherCtxBuilder<'_> {
+    fn default() -> Self {
+        CipherCtxBuilder::new()
+    }
+}
+
+impl<'a> CipherCtxBuilder<'a> {
+    /// Creates a new builder with padding enabled and nothing else set.
+    pub fn new() -> CipherCtxBuilder<'a> {
+        CipherCtxBuilder {
+            cipher: None,
+            key: None,
+            iv: None,
+            padding: true,
+            aad: None,
+            tag_len: None,
+        }
+    }
+
+    /// Sets the cipher. This is required.
+    pub fn cipher(&mut self, cipher: &'a CipherRef) -> &mut Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Sets the key. This is required.
+    ///
+    /// The key must have the cipher's key length, unless the cipher supports variable key lengths.
+    pub fn key(&mut self, key: &'a [u8]) -> &mut Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Sets the IV. This is required if the cipher uses one.
+    ///
+    /// The IV must have the cipher's IV length, unless the cipher is authenticated and supports other IV lengths.
+    pub fn iv(&mut self, iv: &'a [u8]) -> &mut Self {
+        self.iv = Some(iv);
+        self
+    }
+
+    /// Enables or disables padding. Padding is enabled by default.
+    pub fn padding(&mut self, padding: bool) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the additional authenticated data, which is only supported by authenticated ciphers.
+    pub fn aad(&mut self, aad: &'a [u8]) -> &mut Self {
+        self.aad = Some(aad);
+        self
+    }
+
+    /// Sets the length of the authentication tag, which is only supported by authenticated ciphers.
+    ///
+    /// The length is checked against [`CipherCtxRef::valid_tag_lengths`]. CCM and OCB mode ciphers need the tag
+    /// length before the key is set, so it is configured on the context; other modes take it from the tag buffer
+    /// passed to [`CipherCtxRef::tag`] or [`CipherCtxRef::set_tag`].
+    pub fn tag_len(&mut self, len: usize) -> &mut Self {
+        self.tag_len = Some(len);
+        self
+    }
+
+    /// Builds a context initialized for encryption.
+    pub fn build_encrypt(&self) -> Result<CipherCtx, ErrorStack> {
+        self.build(true)
+    }
+
+    /// Builds a context initialized for decryption.
+    pub fn build_decrypt(&self) -> Result<CipherCtx, ErrorStack> {
+        self.build(false)
+    }
+
+    fn build(&self, encrypting: bool) -> Result<CipherCtx, ErrorStack> {
+        let cipher = self
+            .cipher
+            .ok_or_else(|| ErrorStack::internal_error("no cipher set"))?;
+        let key = self
+            .key
+            .ok_or_else(|| ErrorStack::internal_error("no key set"))?;
+
+        let mut ctx = CipherCtx::new()?;
+        if encrypting {
+            ctx.encrypt_init(Some(cipher), None, None)?;
+        } else {
+            ctx.decrypt_init(Some(cipher), None, None)?;
+        }
+        let aead = ctx.is_aead();
+
+        if let Some(len) = self.tag_len {
+            if !ctx.valid_tag_lengths().contains(&len) {
+                return Err(ErrorStack::internal_error(&format!(
+                    "unsupported tag length {}",
+                    len
+                )));
+            }
+            if let CipherMode::Ccm | CipherMode::Ocb = ctx.cipher_mode() {
+                ctx.set_tag_length(len)?;
+            }
+        }
+
+        let iv_len = ctx.iv_length();
+        match self.iv {
+            Some(iv) if iv.len() != iv_len => {
+                if !aead {
+                    return Err(ErrorStack::internal_error(&format!(
+                        "IV must be {} bytes, got {}",
+                        iv_len,
+                        iv.len()
+                    )));
+                }
+                ctx.set_iv_length(iv.len())?;
+            }
+            None if iv_len != 0 => return Err(ErrorStack::internal_error("no IV set")),
+            _ => {}
+        }
+
+        let key_len = ctx.key_length();
+        if key.len() != key_len && ctx.set_key_length(key.len()).is_err() {
+            return Err(ErrorStack::internal_error(&format!(
+                "key must be {} bytes, got {}",
+                key_len,
+                key.len()
+            )));
+        }
+
+        if self.aad.is_some() && !aead {
+            return Err(ErrorStack::internal_error(
+                "AAD is only supported by authenticated ciphers",
+            ));
+        }
+
+        if encrypting {
+            ctx.encrypt_init(None, Some(key), self.iv)?;
+        } else {
+            ctx.decrypt_init(None, Some(key), self.iv)?;
+        }
+        ctx.set_padding(self.padding);
+        if let Some(aad) = self.aad {
+            ctx.set_aad(aad)?;
+        }
+
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher::Cipher;
+    use std::collections::HashMap;
+    use std::slice;
+
+    #[test]
+    fn seal_open() {
+        let private_pem = include_bytes!("../test/rsa.pem");
+        let public_pem = include_bytes!("../test/rsa.pem.pub");
+        let private_key = PKey::private_key_from_pem(private_pem).unwrap();
+        let public_key = PKey::public_key_from_pem(public_pem).unwrap();
+        let cipher = Cipher::aes_256_cbc();
+        let secret = b"My secret message";
+
+        let mut ctx = CipherCtx::new().unwrap();
+        let mut encrypted_key = vec![];
+        let mut iv = vec![0; cipher.iv_length()];
+        let mut encrypted = vec![];
+        ctx.seal_init(
+            Some(cipher),
+            &[public_key],
+            slice::from_mut(&mut encrypted_key),
+            Some(&mut iv),
+        )
+        .unwrap();
+        ctx.cipher_update_vec(secret, &mut encrypted).unwrap();
+        ctx.cipher_final_vec(&mut encrypted).unwrap();
+
+        let mut decrypted = vec![];
+        ctx.open_init(Some(cipher), &encrypted_key, Some(&iv), Some(&private_key))
+            .unwrap();
+        ctx.cipher_update_vec(&encrypted, &mut decrypted).unwrap();
+        ctx.cipher_final_vec(&mut decrypted).unwrap();
+
+        assert_eq!(secret, &decrypted[..]);
+    }
+
+    #[test]
+    fn seal_init_iter() {
+        let mut registry = HashMap::new();
+        registry.insert(
+            "alice",
+            PKey::private_key_from_pem(include_bytes!("../test/rsa.pem")).unwrap(),
+        );
+        registry.insert(
+            "bob",
+            PKey::private_key_from_pem(include_bytes!("../test/key.pem")).unwrap(),
+        );
+
+        let cipher = Cipher::aes_256_cbc();
+        let secret = b"My secret message";
+        let recipients = ["alice", "bob"];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        let mut encrypted_keys = vec![vec![]; 2];
+        let mut iv = vec![0; cipher.iv_length()];
+        ctx.seal_init_iter(
+            Some(cipher),
+            recipients.iter().map(|name| &*registry[name]),
+            &mut encrypted_keys,
+            Some(&mut iv),
+        )
+        .unwrap();
+        let mut encrypted = vec![];
+        ctx.cipher_update_vec(secret, &mut encrypted).unwrap();
+        ctx.cipher_final_vec(&mut encrypted).unwrap();
+
+        for (name, encrypted_key) in recipients.iter().zip(&encrypted_keys) {
+            let mut decrypted = vec![];
+            ctx.open_init(
+                Some(cipher),
+                encrypted_key,
+                Some(&iv),
+                Some(&registry[name]),
+            )
+            .unwrap();
+            ctx.cipher_update_vec(&encrypted, &mut decrypted).unwrap();
+            ctx.cipher_final_vec(&mut decrypted).unwrap();
+            assert_eq!(secret, &decrypted[..]);
+        }
+    }
+
+    fn aes_128_cbc(cipher: &CipherRef) {
+        // from https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf
+        let key = hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let iv = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let pt = hex::decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51")
+            .unwrap();
+        let ct = hex::decode("7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2")
+            .unwrap();
+
+        let mut ctx = CipherCtx::new().unwrap();
+
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.set_padding(false);
+
+        let mut buf = vec![];
+        ctx.cipher_update_vec(&pt, &mut buf).unwrap();
+        ctx.cipher_final_vec(&mut buf).unwrap();
+
+        assert_eq!(buf, ct);
+
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.set_padding(false);
+
+        let mut buf = vec![];
+        ctx.cipher_update_vec(&ct, &mut buf).unwrap();
+        ctx.cipher_final_vec(&mut buf).unwrap();
+
+        assert_eq!(buf, pt);
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn fetched_aes_128_cbc() {
+        let cipher = Cipher::fetch(None, "AES-128-CBC", None).unwrap();
+        aes_128_cbc(&cipher);
+    }
+
+    #[test]
+    fn default_aes_128_cbc() {
+        let cipher = Cipher::aes_128_cbc();
+        aes_128_cbc(cipher);
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn init_fetched() {
+        let key = [0; 16];
+        let iv = [0; 16];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init_fetched(
+            "AES-128-CBC",
+            Some("provider=default"),
+            Some(&key),
+            Some(&iv),
+        )
+        .unwrap();
+        let mut ct = vec![];
+        ctx.cipher_update_vec(b"hello world", &mut ct).unwrap();
+        ctx.cipher_final_vec(&mut ct).unwrap();
+
+        ctx.decrypt_init_fetched("AES-128-CBC", None, Some(&key), Some(&iv))
+            .unwrap();
+        let mut pt = vec![];
+        ctx.cipher_update_vec(&ct, &mut pt).unwrap();
+        ctx.cipher_final_vec(&mut pt).unwrap();
+        assert_eq!(pt, b"hello world");
+
+        assert!(ctx
+            .encrypt_init_fetched("AES-128-CBC", Some("provider=bogus"), None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn rand_key_vec() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_256_cbc()), None, None)
+            .unwrap();
+        let key = ctx.rand_key_vec().unwrap();
+        assert_eq!(key.len(), 32);
+        assert!(key.iter().any(|b| *b != 0));
+        assert_ne!(ctx.rand_key_vec().unwrap(), key);
+    }
+
+    #[test]
+    fn is_encrypting() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&[0; 16]), Some(&[0; 12]))
+            .unwrap();
+        assert!(ctx.is_encrypting());
+        assert!(ctx.try_clone().unwrap().is_encrypting());
+
+        ctx.decrypt_init(Some(Cipher::aes_128_gcm()), Some(&[0; 16]), Some(&[0; 12]))
+            .unwrap();
+        assert!(!ctx.is_encrypting());
+    }
+
+    #[test]
+    #[should_panic]
+    fn is_encrypting_uninitialized() {
+        CipherCtx::new().unwrap().is_encrypting();
+    }
+
+    #[test]
+    #[cfg_attr(ossl300, ignore)]
+    #[cfg(not(osslconf = "OPENSSL_NO_RC2"))]
+    fn rc2_key_bits() {
+        // RFC 2268 section 5, in CBC mode with a zero IV to encrypt a single block as in ECB mode.
+        let key = hex::decode("88bca90e90875a7f0f79c384627bafb2").unwrap();
+        let encrypt = |bits, pt: &[u8]| {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(Cipher::rc2_cbc()), None, None)
+                .unwrap();
+            ctx.set_rc2_key_bits(bits).unwrap();
+            assert_eq!(ctx.rc2_key_bits().unwrap(), bits);
+            ctx.encrypt_init(None, Some(&key), Some(&[0; 8])).unwrap();
+            ctx.set_padding(pt.len() != 8);
+
+            let mut ct = vec![];
+            ctx.cipher_update_vec(pt, &mut ct).unwrap();
+            ctx.cipher_final_vec(&mut ct).unwrap();
+            ct
+        };
+
+        assert_eq!(hex::encode(encrypt(64, &[0; 8])), "1a807d272bbe5db1");
+        assert_eq!(hex::encode(encrypt(128, &[0; 8])), "2269552ab0f85ca6");
+
+        let pt = b"legacy message";
+        let ct = encrypt(40, pt);
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::rc2_cbc()), None, None)
+            .unwrap();
+        ctx.set_rc2_key_bits(40).unwrap();
+        ctx.decrypt_init(None, Some(&key), Some(&[0; 8])).unwrap();
+        let mut out = vec![];
+        ctx.cipher_update_vec(&ct, &mut out).unwrap();
+        ctx.cipher_final_vec(&mut out).unwrap();
+        assert_eq!(out, pt);
+
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), None, None)
+            .unwrap();
+        assert!(ctx.set_rc2_key_bits(40).is_err());
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn rc2_key_bits_provided() {
+        use crate::lib_ctx::LibCtx;
+
+        // RC2 lives in the legacy provider, so it is loaded into a separate library context to exercise the
+        // parameter-based path without affecting the rest of the process.
+        let libctx = LibCtx::new().unwrap();
+        let legacy = unsafe {
+            ffi::OSSL_PROVIDER_try_load(libctx.as_ptr(), b"legacy\0".as_ptr() as *const c_char, 1)
+        };
+        if legacy.is_null() {
+            // the legacy provider module is not installed
+            ErrorStack::get();
+            return;
+        }
+
+        let cipher = Cipher::fetch(Some(&libctx), "RC2-CBC", None).unwrap();
+        let key = hex::decode("88bca90e90875a7f0f79c384627bafb2").unwrap();
+        for &(bits, expected) in &[(64, "1a807d272bbe5db1"), (128, "2269552ab0f85ca6")] {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(&cipher), None, None).unwrap();
+            ctx.set_rc2_key_bits(bits).unwrap();
+            assert_eq!(ctx.rc2_key_bits().unwrap(), bits);
+            ctx.encrypt_init(None, Some(&key), Some(&[0; 8])).unwrap();
+            ctx.set_padding(false);
+
+            let mut ct = vec![];
+            ctx.cipher_update_vec(&[0; 8], &mut ct).unwrap();
+            ctx.cipher_final_vec(&mut ct).unwrap();
+            assert_eq!(hex::encode(ct), expected);
+        }
+
+        drop(cipher);
+        unsafe {
+            ffi::OSSL_PROVIDER_unload(legacy);
+        }
+    }
+
+    #[test]
+    fn set_key_length_fixed() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), None, None)
+            .unwrap();
+        assert!(ctx.set_key_length(10).is_err());
+        assert_eq!(ctx.key_length(), 16);
+        ctx.set_key_length(16).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(ossl300, ignore)]
+    #[cfg(not(osslconf = "OPENSSL_NO_RC2"))]
+    fn set_key_length_variable() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::rc2_cbc()), None, None)
+            .unwrap();
+        ctx.set_key_length(10).unwrap();
+        assert_eq!(ctx.key_length(), 10);
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn set_key_length_provided_only() {
+        // SIV has no built-in cipher, so the flag lookup falls back to letting OpenSSL validate the length.
+        let cipher = Cipher::fetch(None, "AES-128-SIV", None).unwrap();
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(&cipher), None, None).unwrap();
+        assert!(ctx.has_variable_key_length());
+        assert!(ctx.set_key_length(10).is_err());
+        ctx.set_key_length(32).unwrap();
+        assert_eq!(ctx.key_length(), 32);
+    }
+
+    #[test]
+    fn iv_gen() {
+        let key = [0x42; 16];
+        let fixed = [1, 2, 3, 4];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&key), None)
+            .unwrap();
+        ctx.set_iv_fixed(&fixed).unwrap();
+
+        let mut records = vec![];
+        for msg in [&b"first record"[..], &b"second record"[..]].iter() {
+            let mut explicit = [0; 8];
+            ctx.iv_gen(&mut explicit).unwrap();
+            ctx.set_aad(b"header").unwrap();
+            let mut ct = vec![];
+            ctx.cipher_update_vec(msg, &mut ct).unwrap();
+            ctx.cipher_final_vec(&mut ct).unwrap();
+            let mut tag = [0; 16];
+            ctx.tag(&mut tag).unwrap();
+            records.push((explicit, ct, tag));
+        }
+
+        let counter = |explicit: &[u8; 8]| u64::from_be_bytes(*explicit);
+        assert_eq!(
+            counter(&records[1].0),
+            counter(&records[0].0).wrapping_add(1)
+        );
+
+        for ((explicit, ct, tag), msg) in records
+            .iter()
+            .zip([&b"first record"[..], &b"second record"[..]].iter())
+        {
+            let mut iv = fixed.to_vec();
+            iv.extend_from_slice(explicit);
+
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.decrypt_init(Some(Cipher::aes_128_gcm()), Some(&key), Some(&iv))
+                .unwrap();
+            ctx.set_aad(b"header").unwrap();
+            let mut pt = vec![];
+            ctx.cipher_update_vec(ct, &mut pt).unwrap();
+            ctx.set_tag(tag).unwrap();
+            ctx.cipher_final_vec(&mut pt).unwrap();
+            assert_eq!(pt, *msg);
+        }
+
+        assert!(ctx.set_iv_fixed(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn cipher_name() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), None, None)
+            .unwrap();
+        assert_eq!(ctx.cipher_name(), "AES-128-CBC");
+    }
+
+    #[test]
+    #[should_panic]
+    fn cipher_name_uninitialized() {
+        let ctx = CipherCtx::new().unwrap();
+        let _ = ctx.cipher_name();
+    }
+
+    #[test]
+    fn provider_name() {
+        let mut ctx = CipherCtx::new().unwrap();
+        assert_eq!(ctx.provider_name(), None);
+
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), None, None)
+            .unwrap();
+        #[cfg(ossl300)]
+        assert!(ctx.provider_name().is_some());
+        #[cfg(not(ossl300))]
+        assert_eq!(ctx.provider_name(), None);
+    }
+
+    #[test]
+    #[cfg(all(ossl110, not(osslconf = "OPENSSL_NO_CHACHA")))]
+    fn chacha20_init() {
+        // RFC 8439 appendix A.1, test vector #1
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.chacha20_init(&[0; 32], 0, &[0; 12], true).unwrap();
+        let mut out = vec![];
+        ctx.cipher_update_vec(&[0; 64], &mut out).unwrap();
+        assert_eq!(
+            hex::encode(&out),
+            "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7\
+             da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586"
+        );
+
+        // RFC 8439 section 2.4.2
+        let mut key = [0; 32];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let plaintext = b"Ladies and Gentlemen of the class of '99: \
+                          If I could offer you only one tip for the future, sunscreen would be it.";
+        let ciphertext = "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+                          f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+                          07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+                          5af90bbf74a35be6b40b8eedf2785e42874d";
+
+        ctx.chacha20_init(&key, 1, &nonce, true).unwrap();
+        let mut out = vec![];
+        ctx.cipher_update_vec(plaintext, &mut out).unwrap();
+        ctx.cipher_final_vec(&mut out).unwrap();
+        assert_eq!(hex::encode(&out), ciphertext);
+
+        ctx.chacha20_init(&key, 1, &nonce, false).unwrap();
+        let mut out = vec![];
+        ctx.cipher_update_vec(&hex::decode(ciphertext).unwrap(), &mut out)
+            .unwrap();
+        ctx.cipher_final_vec(&mut out).unwrap();
+        assert_eq!(&out[..], &plaintext[..]);
+    }
+
+    #[test]
+    fn builder_gcm() {
+        let key = [1; 32];
+        let iv = [2; 12];
+        let mut ctx = CipherCtxBuilder::new()
+            .cipher(Cipher::aes_256_gcm())
+            .key(&key)
+            .iv(&iv)
+            .aad(b"header")
+            .build_encrypt()
+            .unwrap();
+        let mut ciphertext = vec![];
+        ctx.cipher_update_vec(b"hello world", &mut ciphertext)
+            .unwrap();
+        ctx.cipher_final_vec(&mut ciphertext).unwrap();
+        let mut tag = [0; 16];
+        ctx.tag(&mut tag).unwrap();
+
+        let mut ctx = CipherCtxBuilder::new()
+            .cipher(Cipher::aes_256_gcm())
+            .key(&key)
+            .iv(&iv)
+            .aad(b"header")
+            .tag_len(16)
+            .build_decrypt()
+            .unwrap();
+        ctx.set_tag(&tag).unwrap();
+        let mut plaintext = vec![];
+        ctx.cipher_update_vec(&ciphertext, &mut plaintext).unwrap();
+        ctx.cipher_final_vec(&mut plaintext).unwrap();
+        assert_eq!(plaintext, b"hello world");
+
+        let mut builder = CipherCtxBuilder::new();
+        builder.cipher(Cipher::aes_256_gcm()).key(&key).iv(&iv);
+        assert!(builder.tag_len(3).build_encrypt().is_err());
+        assert!(builder.tag_len(16).iv(&[2; 16]).build_encrypt().is_ok());
+    }
+
+    #[test]
+    fn builder_cbc() {
+        let key = [1; 16];
+        let iv = [2; 16];
+        let mut ctx = CipherCtxBuilder::new()
+            .cipher(Cipher::aes_128_cbc())
+            .key(&key)
+            .iv(&iv)
+            .build_encrypt()
+            .unwrap();
+        let mut ciphertext = vec![];
+        ctx.cipher_update_vec(b"hello world", &mut ciphertext)
+            .unwrap();
+        ctx.cipher_final_vec(&mut ciphertext).unwrap();
+        assert_eq!(ciphertext.len(), 16);
+
+        let mut ctx = CipherCtxBuilder::new()
+            .cipher(Cipher::aes_128_cbc())
+            .key(&key)
+            .iv(&iv)
+            .build_decrypt()
+            .unwrap();
+        let mut plaintext = vec![];
+        ctx.cipher_update_vec(&ciphertext, &mut plaintext).unwrap();
+        ctx.cipher_final_vec(&mut plaintext).unwrap();
+        assert_eq!(plaintext, b"hello world");
+
+        let ctx = CipherCtxBuilder::new()
+            .cipher(Cipher::aes_128_cbc())
+            .key(&key)
+            .iv(&iv)
+            .padding(false)
+            .build_encrypt()
+            .unwrap();
+        assert!(!ctx.padding());
+
+        assert!(CipherCtxBuilder::new().key(&key).build_encrypt().is_err());
+        let mut builder = CipherCtxBuilder::new();
+        builder.cipher(Cipher::aes_128_cbc()).key(&key);
+        assert!(builder.build_encrypt().is_err());
+        assert!(builder.iv(&[2; 12]).build_encrypt().is_err());
+        assert!(builder.iv(&iv).aad(b"header").build_encrypt().is_err());
+        let mut builder = CipherCtxBuilder::new();
+        builder.cipher(Cipher::aes_128_cbc()).iv(&iv);
+        match builder.key(&[1; 32]).build_encrypt() {
+            Err(e) => assert!(e.errors()[0].data().unwrap().contains("16 bytes")),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn padding() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        assert!(ctx.padding());
+
+        ctx.set_padding(false);
+        assert!(!ctx.padding());
+        let mut out = [0; 32];
+        assert_eq!(ctx.cipher_update(&[0; 16], Some(&mut out)).unwrap(), 16);
+        assert_eq!(ctx.cipher_final(&mut out).unwrap(), 0);
+
+        ctx.set_padding(true);
+        assert!(ctx.padding());
+    }
+
+    #[test]
+    fn check_alignment() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        ctx.check_alignment(17).unwrap();
+
+        ctx.set_padding(false);
+        ctx.check_alignment(32).unwrap();
+        let err = ctx.check_alignment(31).unwrap_err();
+        let error = err.errors().last().unwrap();
+        assert!(error.data().unwrap().contains("block size 16"));
+        assert_eq!(ffi::ERR_GET_LIB(error.code()), ffi::ERR_LIB_USER);
+        assert!(error.file().ends_with("cipher_ctx.rs"));
+        assert!(crate::error::Error::get().is_none());
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_ctr()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        ctx.set_padding(false);
+        ctx.check_alignment(31).unwrap();
+    }
+
+    #[test]
+    fn update_reuse() {
+        let key = [0; 16];
+        let iv = [1; 16];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.set_padding(false);
+
+        let mut buf = Vec::with_capacity(4096);
+        let ptr = buf.as_ptr();
+        let mut ciphertext = vec![];
+        for _ in 0..4 {
+            ctx.cipher_update_reuse(&[7; 1024], &mut buf).unwrap();
+            assert_eq!(buf.len(), 1024);
+            assert_eq!(buf.as_ptr(), ptr);
+            ciphertext.extend_from_slice(&buf);
+        }
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.set_padding(false);
+        let mut expected = vec![];
+        ctx.cipher_update_vec(&[7; 4096], &mut expected).unwrap();
+        assert_eq!(ciphertext, expected);
+    }
+
+    #[test]
+    fn gcm_nonce_generator() {
+        let key = [0x11; 16];
+        let mut gen = GcmNonceGenerator::new(Cipher::aes_128_gcm(), &key, [1, 2, 3, 4]).unwrap();
+
+        let iv1 = gen.next_encrypt_init().unwrap();
+        assert_eq!(iv1, [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut ciphertext = vec![];
+        gen.ctx_mut()
+            .cipher_update_vec(b"hello", &mut ciphertext)
+            .unwrap();
+        gen.ctx_mut().cipher_final_vec(&mut ciphertext).unwrap();
+        let mut tag = [0; 16];
+        gen.ctx().tag(&mut tag).unwrap();
+
+        let iv2 = gen.next_encrypt_init().unwrap();
+        assert_eq!(iv2, [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let mut stream = OpenStream::new(Cipher::aes_128_gcm(), &key, &iv1).unwrap();
+        stream.update(&ciphertext).unwrap();
+        assert_eq!(stream.finalize(&tag).unwrap(), b"hello");
+
+        gen.invocation = u64::MAX;
+        let iv = gen.next_encrypt_init().unwrap();
+        assert_eq!(iv[4..], [0xff; 8]);
+        assert!(gen.next_encrypt_init().is_err());
+    }
+
+    #[test]
+    fn update_aad_stream() {
+        let key = [0; 32];
+        let iv = [0; 12];
+        let aad = vec![0x61; 20000];
+
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::chacha20_poly1305()), Some(&key), Some(&iv))
+            .unwrap();
+        assert_eq!(ctx.update_aad_stream(&mut &aad[..]).unwrap(), 20000);
+        let mut out = vec![];
+        ctx.cipher_update_vec(b"payload", &mut out).unwrap();
+        ctx.cipher_final_vec(&mut out).unwrap();
+        let mut tag = [0; 16];
+        ctx.ctx().tag(&mut tag).unwrap();
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::chacha20_poly1305()), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update(&aad, None).unwrap();
+        let mut expected = vec![];
+        ctx.cipher_update_vec(b"payload", &mut expected).unwrap();
+        ctx.cipher_final_vec(&mut expected).unwrap();
+        let mut expected_tag = [0; 16];
+        ctx.tag(&mut expected_tag).unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(tag, expected_tag);
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn cipher_final_len() {
+        let key = [0; 16];
+        let iv = [0; 16];
+
+        for &len in &[0, 1, 15, 16, 17, 32] {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+                .unwrap();
+            let mut out = vec![];
+            ctx.cipher_update_vec(&vec![0; len], &mut out).unwrap();
+            let expected = ctx.cipher_final_len();
+            let mut buf = [0; 16];
+            assert_eq!(ctx.cipher_final(&mut buf).unwrap(), expected);
+        }
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        assert_eq!(ctx.cipher_final_len(), 15);
+        ctx.set_padding(false);
+        assert_eq!(ctx.cipher_final_len(), 0);
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        assert_eq!(ctx.cipher_final_len(), 0);
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn finalize_record() {
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&key), Some(&iv))
+            .unwrap();
+        let iv2 = [0x25; 12];
+        let mut ciphertext = vec![];
+        ctx.cipher_update_vec(b"record one", &mut ciphertext)
+            .unwrap();
+        let (output, tag) = ctx.finalize_record(&iv2).unwrap();
+        assert!(output.is_empty());
+        let tag = tag.unwrap();
+        assert_eq!(tag.len(), 16);
+
+        let mut stream = OpenStream::new(Cipher::aes_128_gcm(), &key, &iv).unwrap();
+        stream.update(&ciphertext).unwrap();
+        assert_eq!(stream.finalize(&tag).unwrap(), b"record one");
+
+        let mut ciphertext = vec![];
+        ctx.cipher_update_vec(b"record two", &mut ciphertext)
+            .unwrap();
+        let (_, tag) = ctx.finalize_record(&[0x26; 12]).unwrap();
+        let mut stream = OpenStream::new(Cipher::aes_128_gcm(), &key, &iv2).unwrap();
+        stream.update(&ciphertext).unwrap();
+        assert_eq!(stream.finalize(&tag.unwrap()).unwrap(), b"record two");
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&[0; 16]))
+            .unwrap();
+        let mut first = vec![];
+        ctx.cipher_update_vec(b"block", &mut first).unwrap();
+        let (output, tag) = ctx.finalize_record(&[1; 16]).unwrap();
+        first.extend_from_slice(&output);
+        assert_eq!(tag, None);
+
+        let mut second = vec![];
+        ctx.cipher_update_vec(b"block", &mut second).unwrap();
+        let (output, _) = ctx.finalize_record(&[2; 16]).unwrap();
+        second.extend_from_slice(&output);
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&[1; 16]))
+            .unwrap();
+        let mut decrypted = vec![];
+        ctx.cipher_update_vec(&second, &mut decrypted).unwrap();
+        ctx.cipher_final_vec(&mut decrypted).unwrap();
+        assert_eq!(decrypted, b"block");
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn finalize_record_ofb() {
+        let key = [0x42; 16];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ofb()), Some(&key), Some(&[0; 16]))
+            .unwrap();
+        let mut first = vec![];
+        ctx.cipher_update_vec(&[0; 32], &mut first).unwrap();
+        let (output, _) = ctx.finalize_record(&[1; 16]).unwrap();
+        first.extend_from_slice(&output);
+
+        let mut second = vec![];
+        ctx.cipher_update_vec(&[0; 32], &mut second).unwrap();
+        let (output, _) = ctx.finalize_record(&[2; 16]).unwrap();
+        second.extend_from_slice(&output);
+
+        // Encrypting zeros exposes the keystream, which must differ between records.
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(ossl110)]
+    fn finalize_record_short_iv() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ofb()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        let _ = ctx.finalize_record(&[]);
+    }
+
+    #[test]
+    fn scratch() {
+        let key = [0; 16];
+        let iv = [0; 16];
+        let plaintext = vec![0x33; 50000];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut expected = vec![];
+        ctx.cipher_update_vec(&plaintext, &mut expected).unwrap();
+        ctx.cipher_final_vec(&mut expected).unwrap();
+
+        let mut scratch = Vec::with_capacity(16384);
+        let ptr = scratch.as_ptr();
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut ciphertext = vec![];
+        {
+            let mut cipher = ctx.with_scratch(&mut scratch);
+            let read = cipher
+                .update_stream(&mut &plaintext[..49000], &mut ciphertext)
+                .unwrap();
+            assert_eq!(read, 49000);
+            ciphertext.extend_from_slice(cipher.update(&plaintext[49000..]).unwrap());
+            ciphertext.extend_from_slice(cipher.finalize().unwrap());
+        }
+        assert_eq!(ciphertext, expected);
+        assert_eq!(scratch.as_ptr(), ptr);
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn removed_padding_bytes() {
+        let key = [0; 16];
+        let iv = [0; 16];
+
+        for &len in &[0, 5, 16, 31] {
+            let mut ctx = CipherSession::new().unwrap();
+            ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+                .unwrap();
+            let mut ciphertext = vec![];
+            ctx.cipher_update_vec(&vec![1; len], &mut ciphertext)
+                .unwrap();
+            ctx.cipher_final_vec(&mut ciphertext).unwrap();
+            assert_eq!(ctx.removed_padding_bytes(), None);
+
+            let mut ctx = CipherSession::new().unwrap();
+            ctx.decrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+                .unwrap();
+            assert_eq!(ctx.removed_padding_bytes(), None);
+            let mut plaintext = vec![];
+            ctx.cipher_update_vec(&ciphertext, &mut plaintext).unwrap();
+            ctx.cipher_final_vec(&mut plaintext).unwrap();
+            assert_eq!(plaintext.len(), len);
+            assert_eq!(ctx.removed_padding_bytes(), Some(16 - len % 16));
+        }
+
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut plaintext = vec![];
+        ctx.cipher_update_vec(b"stream", &mut plaintext).unwrap();
+        ctx.cipher_final_vec(&mut plaintext).unwrap();
+        assert_eq!(ctx.removed_padding_bytes(), None);
+    }
+
+    #[test]
+    fn valid_tag_lengths() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), None, None)
+            .unwrap();
+        assert_eq!(ctx.valid_tag_lengths(), [4, 8, 12, 13, 14, 15, 16]);
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ccm()), None, None)
+            .unwrap();
+        assert_eq!(ctx.valid_tag_lengths(), [4, 6, 8, 10, 12, 14, 16]);
+        ctx.set_tag_length(10).unwrap();
+        assert!(ctx.set_tag_length(11).is_err());
+
+        #[cfg(ossl110)]
+        {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(Cipher::chacha20_poly1305()), None, None)
+                .unwrap();
+            assert_eq!(ctx.valid_tag_lengths().len(), 16);
+        }
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), None, None)
+            .unwrap();
+        assert!(ctx.valid_tag_lengths().is_empty());
+    }
+
+    #[test]
+    fn seal_records() {
+        let key = [7; 16];
+        let base_nonce = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff];
+        let records: &[&[u8]] = &[b"first", b"second", b""];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        let sealed = ctx
+            .seal_records(Cipher::aes_128_gcm(), &key, &base_nonce, records, b"header")
+            .unwrap();
+        assert_eq!(sealed.len(), 3);
+
+        let nonces = [
+            base_nonce,
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0],
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1],
+        ];
+        for ((record, (ciphertext, tag)), nonce) in records.iter().zip(&sealed).zip(&nonces) {
+            let mut stream = OpenStream::new(Cipher::aes_128_gcm(), &key, nonce).unwrap();
+            stream.aad_update(b"header").unwrap();
+            stream.update(ciphertext).unwrap();
+            assert_eq!(stream.finalize(tag).unwrap(), *record);
+        }
+
+        let mut ctx = CipherCtx::new().unwrap();
+        assert!(ctx
+            .seal_records(Cipher::aes_128_gcm(), &key, &[0xff; 12], records, b"")
+            .is_err());
+    }
+
+    #[test]
+    fn authenticate() {
+        let key = [3; 16];
+        let iv = [4; 12];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&key), Some(&iv))
+            .unwrap();
+        let (ciphertext, tag) = ctx
+            .authenticate(b"head")
+            .unwrap()
+            .authenticate(b"er")
+            .unwrap()
+            .encrypt(b"body")
+            .unwrap();
+
+        let mut stream = OpenStream::new(Cipher::aes_128_gcm(), &key, &iv).unwrap();
+        stream.aad_update(b"header").unwrap();
+        stream.update(&ciphertext).unwrap();
+        assert_eq!(stream.finalize(&tag).unwrap(), b"body");
+
+        ctx.encrypt_init(None, None, Some(&iv)).unwrap();
+        let tag = ctx.authenticate(b"header").unwrap().finish().unwrap();
+        let mut stream = OpenStream::new(Cipher::aes_128_gcm(), &key, &iv).unwrap();
+        stream.aad_update(b"header").unwrap();
+        assert!(stream.finalize(&tag).unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_with_hash() {
+        use crate::hash::{hash, MessageDigest};
+
+        let key = [0; 16];
+        let iv = [0; 16];
+        let plaintext = [0x42; 100];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut ciphertext = vec![];
+        let mut ciphertext_hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        for chunk in plaintext.chunks(30) {
+            ctx.cipher_update_with_hash(chunk, &mut ciphertext, &mut ciphertext_hasher, false)
+                .unwrap();
+        }
+        assert_eq!(
+            *ciphertext_hasher.finish().unwrap(),
+            *hash(MessageDigest::sha256(), &ciphertext).unwrap()
+        );
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut decrypted = vec![];
+        let mut plaintext_hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        let len = ctx
+            .cipher_update_with_hash(&ciphertext, &mut decrypted, &mut plaintext_hasher, false)
+            .unwrap();
+        assert_eq!(len, 100);
+        assert_eq!(decrypted, plaintext);
+        assert_eq!(
+            *plaintext_hasher.finish().unwrap(),
+            *hash(MessageDigest::sha256(), &plaintext).unwrap()
+        );
+
+        let mut input_hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update_with_hash(&plaintext, &mut vec![], &mut input_hasher, true)
+            .unwrap();
+        assert_eq!(
+            *input_hasher.finish().unwrap(),
+            *hash(MessageDigest::sha256(), &plaintext).unwrap()
+        );
+    }
+
+    #[test]
+    fn final_auto() {
+        let cipher = Cipher::aes_128_cbc();
+        let key = [0; 16];
+        let iv = [0; 16];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut buf = vec![];
+        ctx.cipher_update_vec(b"hello world", &mut buf).unwrap();
+        assert!(buf.is_empty());
+        let last = ctx.cipher_final_auto().unwrap();
+        assert_eq!(last.len(), 16);
+
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut buf = vec![];
+        ctx.cipher_update_vec(&last, &mut buf).unwrap();
+        buf.extend_from_slice(&ctx.cipher_final_auto().unwrap());
+        assert_eq!(buf, b"hello world");
+    }
+
+    #[test]
+    fn update_ring() {
+        struct Ring {
+            buf: Vec<u8>,
+            head: usize,
+            len: usize,
+        }
+
+        impl RingBuffer for Ring {
+            fn writable(&mut self) -> (&mut [u8], &mut [u8]) {
+                let tail = (self.head + self.len) % self.buf.len();
+                if self.len != 0 && tail <= self.head {
+                    (&mut self.buf[tail..self.head], &mut [])
+                } else {
+                    let (start, end) = self.buf.split_at_mut(tail);
+                    (end, &mut start[..self.head])
+                }
+            }
+
+            fn commit(&mut self, len: usize) {
+                self.len += len;
+            }
+        }
+
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+        let plaintext = [0x55; 70];
+
+        for cipher in &[Cipher::aes_128_cbc(), Cipher::aes_128_ctr()] {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            let mut expected = vec![];
+            ctx.cipher_update_vec(&plaintext, &mut expected).unwrap();
+
+            let mut ring = Ring {
+                buf: vec![0; 100],
+                head: 90,
+                len: 5,
+            };
+            // writing starts 5 bytes before the wrap-around point
+            let start = 95;
+
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            let mut written = 0;
+            for chunk in plaintext.chunks(13) {
+                written += ctx.cipher_update_ring(chunk, &mut ring).unwrap();
+            }
+            assert_eq!(written, expected.len());
+
+            let actual = (0..written)
+                .map(|i| ring.buf[(start + i) % ring.buf.len()])
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected);
+
+            assert!(ctx.cipher_update_ring(&[0; 30], &mut ring).is_err());
+        }
+    }
+
+    #[test]
+    fn seal_open_packet() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+        let header = b"header";
+        let plaintext = b"Lorem ipsum dolor sit amet";
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut body = plaintext.to_vec();
+        let mut tag = [0; 16];
+        ctx.seal_packet(header, &mut body, &mut tag).unwrap();
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update(header, None).unwrap();
+        let mut expected = vec![];
+        ctx.cipher_update_vec(plaintext, &mut expected).unwrap();
+        ctx.cipher_final_vec(&mut expected).unwrap();
+        let mut expected_tag = [0; 16];
+        ctx.tag(&mut expected_tag).unwrap();
+        assert_eq!(body, expected);
+        assert_eq!(tag, expected_tag);
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut opened = body.clone();
+        ctx.open_packet(header, &mut opened, &tag).unwrap();
+        assert_eq!(opened, plaintext);
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut opened = body.clone();
+        assert!(ctx.open_packet(b"other", &mut opened, &tag).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn seal_packet_non_aead() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        let _ = ctx.seal_packet(&[], &mut [0; 16], &mut [0; 16]);
+    }
+
+    #[test]
+    fn keyed_aead() {
+        let mut keyed = KeyedAead::new(Cipher::aes_128_gcm(), &[0x42; 16]);
+        let mut a = keyed.new_stream().unwrap();
+        let mut b = keyed.new_stream().unwrap();
+
+        let mut ivs = vec![];
+        for stream in [&mut a, &mut b].iter_mut() {
+            for _ in 0..3 {
+                ivs.push(stream.next_encrypt_init().unwrap());
+            }
+        }
+        for (i, iv) in ivs.iter().enumerate() {
+            assert!(!ivs[i + 1..].contains(iv));
+        }
+        assert_eq!(ivs[0], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ivs[4], [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let iv = b.next_encrypt_init().unwrap();
+        let mut ct = vec![];
+        b.ctx_mut()
+            .cipher_update_vec(b"hello world", &mut ct)
+            .unwrap();
+        b.ctx_mut().cipher_final_vec(&mut ct).unwrap();
+        let mut tag = [0; 16];
+        b.ctx().tag(&mut tag).unwrap();
+
+        let mut ctx = keyed.decrypt_ctx(&iv).unwrap();
+        ctx.set_tag(&tag).unwrap();
+        let mut pt = vec![];
+        ctx.cipher_update_vec(&ct, &mut pt).unwrap();
+        ctx.cipher_final_vec(&mut pt).unwrap();
+        assert_eq!(pt, b"hello world");
+    }
+
+    #[test]
+    fn pad_to() {
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+
+        for &(cipher, overhead) in &[(Cipher::aes_128_cbc(), 16), (Cipher::aes_128_ctr(), 0)] {
+            for &(len, padded) in &[(0, 64), (10, 64), (63, 64), (64, 128), (100, 128)] {
+                let plaintext = vec![0x55; len];
+
+                let mut ctx = CipherSession::new().unwrap();
+                ctx.set_pad_to(64);
+                ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+                    .unwrap();
+                let mut ciphertext = vec![];
+                ctx.cipher_update_vec(&plaintext, &mut ciphertext).unwrap();
+                let final_len = ctx.final_output_len(len);
+                assert_eq!(ctx.cipher_final_vec(&mut ciphertext).unwrap(), final_len);
+                assert_eq!(ciphertext.len(), padded + overhead);
+
+                let mut ctx = CipherSession::new().unwrap();
+                ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+                    .unwrap();
+                ctx.set_pad_to(64);
+                let mut decrypted = vec![];
+                for chunk in ciphertext.chunks(7) {
+                    ctx.cipher_update_vec(chunk, &mut decrypted).unwrap();
+                }
+                ctx.cipher_final_vec(&mut decrypted).unwrap();
+                assert_eq!(decrypted, plaintext);
+            }
+
+            let mut ctx = CipherSession::new().unwrap();
+            ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            let mut ciphertext = vec![];
+            ctx.cipher_update_vec(&[0x55; 64], &mut ciphertext).unwrap();
+            ctx.cipher_final_vec(&mut ciphertext).unwrap();
+
+            let mut ctx = CipherSession::new().unwrap();
+            ctx.set_pad_to(64);
+            ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            let mut decrypted = vec![];
+            ctx.cipher_update_vec(&ciphertext, &mut decrypted).unwrap();
+            assert!(ctx.cipher_final_vec(&mut decrypted).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn num() {
+        let cipher = Cipher::aes_128_ofb();
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+        let data = [0x55; 40];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        assert_eq!(ctx.num(), 0);
+        let mut ciphertext = vec![];
+        ctx.cipher_update_vec(&data[..20], &mut ciphertext).unwrap();
+        let num = ctx.num();
+        assert_eq!(num, 4);
+        ctx.cipher_update_vec(&data[20..], &mut ciphertext).unwrap();
+
+        // in OFB mode, the IV within the second block is the second block of keystream
+        let register: Vec<u8> = ciphertext[16..32]
+            .iter()
+            .zip(&data[16..32])
+            .map(|(c, p)| c ^ p)
+            .collect();
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&register))
+            .unwrap();
+        ctx.set_num(num);
+        assert_eq!(ctx.num(), num);
+        let mut resumed = vec![];
+        ctx.cipher_update_vec(&data[20..], &mut resumed).unwrap();
+        assert_eq!(resumed, &ciphertext[20..]);
+    }
+
+    #[test]
+    fn cipher_mode() {
+        let cases = [
+            (Cipher::aes_128_ecb(), CipherMode::Ecb),
+            (Cipher::aes_128_cbc(), CipherMode::Cbc),
+            (Cipher::aes_128_cfb128(), CipherMode::Cfb),
+            (Cipher::aes_128_ofb(), CipherMode::Ofb),
+            (Cipher::aes_128_ctr(), CipherMode::Ctr),
+            (Cipher::aes_128_gcm(), CipherMode::Gcm),
+            (Cipher::aes_128_ccm(), CipherMode::Ccm),
+            (Cipher::aes_128_xts(), CipherMode::Xts),
+            #[cfg(ossl110)]
+            (Cipher::chacha20(), CipherMode::Stream),
+        ];
+        for (cipher, mode) in cases.iter() {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(cipher), None, None).unwrap();
+            assert_eq!(ctx.cipher_mode(), *mode);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn cipher_mode_uninitialized() {
+        CipherCtx::new().unwrap().cipher_mode();
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn iv() {
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        assert_eq!(ctx.iv().unwrap(), iv);
+
+        // in CBC mode, the IV advances to the last block of ciphertext
+        let mut ciphertext = vec![0; 32];
+        let len = ctx
+            .cipher_update(&[0x55; 16], Some(&mut ciphertext))
+            .unwrap();
+        assert_eq!(ctx.iv().unwrap(), &ciphertext[..len]);
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn iv_gcm() {
+        let key = [0x42; 16];
+        let iv = [0x24; 8];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), None, None)
+            .unwrap();
+        ctx.set_iv_length(8).unwrap();
+        ctx.encrypt_init(None, Some(&key), Some(&iv)).unwrap();
+        assert_eq!(ctx.iv().unwrap(), iv);
+
+        // the GCM counter is internal, so the nonce is still reported after processing data
+        let mut ciphertext = vec![0; 40];
+        ctx.cipher_update(&[0x55; 40], Some(&mut ciphertext))
+            .unwrap();
+        assert_eq!(ctx.iv().unwrap(), iv);
+    }
+
+    #[test]
+    #[cfg(all(ossl110, not(ossl300)))]
+    fn iv_gcm_unsupported() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(
+            Some(Cipher::aes_128_gcm()),
+            Some(&[0x42; 16]),
+            Some(&[0x24; 12]),
+        )
+        .unwrap();
+        assert!(ctx.iv().is_err());
+    }
+
+    #[test]
+    fn set_aad() {
+        let seal = |aad: &[u8]| {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(
+                Some(Cipher::aes_128_gcm()),
+                Some(&[0x42; 16]),
+                Some(&[0x24; 12]),
+            )
+            .unwrap();
+            ctx.set_aad(aad).unwrap();
+            let mut ciphertext = vec![];
+            ctx.cipher_update_vec(b"payload", &mut ciphertext).unwrap();
+            ctx.cipher_final_vec(&mut ciphertext).unwrap();
+            let mut tag = [0; 16];
+            ctx.tag(&mut tag).unwrap();
+            (ciphertext, tag)
+        };
+
+        let (ciphertext, tag) = seal(b"header");
+        let (other_ciphertext, other_tag) = seal(b"other header");
+        assert_eq!(ciphertext, other_ciphertext);
+        assert_ne!(tag, other_tag);
+    }
+
+    #[test]
+    fn session_aad_order() {
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.encrypt_init(
+            Some(Cipher::chacha20_poly1305()),
+            Some(&[0; 32]),
+            Some(&[0; 12]),
+        )
+        .unwrap();
+        ctx.set_aad(b"header").unwrap();
+        assert_eq!(ctx.update_aad_stream(&mut &b"more"[..]).unwrap(), 4);
+        let mut out = vec![];
+        ctx.cipher_update_vec(b"payload", &mut out).unwrap();
+        assert!(ctx.set_aad(b"header").is_err());
+        assert!(ctx.update_aad_stream(&mut &b"more"[..]).is_err());
+        ctx.cipher_final_vec(&mut out).unwrap();
+
+        ctx.encrypt_init(None, None, Some(&[1; 12])).unwrap();
+        ctx.set_aad(b"header").unwrap();
+    }
+
+    #[test]
+    fn one_shot() {
+        let key = [0x42; 32];
+        let iv = [0x24; 16];
+        let data = b"Some Crypto Text with an uneven length";
+
+        let cipher = Cipher::aes_256_cbc();
+        let ciphertext = CipherCtx::encrypt(cipher, &key, Some(&iv), data).unwrap();
+        assert_eq!(ciphertext.len(), 48);
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut expected = vec![];
+        ctx.cipher_update_vec(data, &mut expected).unwrap();
+        ctx.cipher_final_vec(&mut expected).unwrap();
+        assert_eq!(ciphertext, expected);
+        assert_eq!(
+            CipherCtx::decrypt(cipher, &key, Some(&iv), &ciphertext).unwrap(),
+            &data[..]
+        );
+
+        let cipher = Cipher::aes_128_gcm();
+        let sealed = CipherCtx::encrypt(cipher, &key[..16], Some(&iv[..12]), data).unwrap();
+        assert_eq!(sealed.len(), data.len() + 16);
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key[..16]), Some(&iv[..12]))
+            .unwrap();
+        let mut expected = vec![];
+        ctx.cipher_update_vec(data, &mut expected).unwrap();
+        ctx.cipher_final_vec(&mut expected).unwrap();
+        let mut tag = [0; 16];
+        ctx.tag(&mut tag).unwrap();
+        assert_eq!(&sealed[..data.len()], &expected[..]);
+        assert_eq!(&sealed[data.len()..], &tag[..]);
+        assert_eq!(
+            CipherCtx::decrypt(cipher, &key[..16], Some(&iv[..12]), &sealed).unwrap(),
+            &data[..]
+        );
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(CipherCtx::decrypt(cipher, &key[..16], Some(&iv[..12]), &tampered).is_err());
+        assert!(CipherCtx::decrypt(cipher, &key[..16], Some(&iv[..12]), &sealed[..15]).is_err());
+
+        let cipher = Cipher::aes_128_ccm();
+        let sealed = CipherCtx::encrypt(cipher, &key[..16], Some(&iv[..12]), data).unwrap();
+        assert_eq!(sealed.len(), data.len() + 16);
+        assert_eq!(
+            CipherCtx::decrypt(cipher, &key[..16], Some(&iv[..12]), &sealed).unwrap(),
+            &data[..]
+        );
+
+        // invalid lengths are errors rather than panics
+        let cipher = Cipher::aes_256_cbc();
+        assert!(CipherCtx::encrypt(cipher, &key[..16], Some(&iv), data).is_err());
+        assert!(CipherCtx::encrypt(cipher, &key, Some(&iv[..8]), data).is_err());
+        assert!(CipherCtx::decrypt(cipher, &key, Some(&iv), &ciphertext[..20]).is_err());
+    }
+
+    #[test]
+    fn ccm() {
+        // NIST SP 800-38C appendix C, examples 1 to 3
+        let key = hex::decode("404142434445464748494a4b4c4d4e4f").unwrap();
+        let vectors = [
+            (
+                "10111213141516",
+                "0001020304050607",
+                "20212223",
+                "7162015b",
+                "4dac255d",
+            ),
+            (
+                "1011121314151617",
+                "000102030405060708090a0b0c0d0e0f",
+                "202122232425262728292a2b2c2d2e2f",
+                "d2a1f0e051ea5f62081a7792073d593d",
+                "1fc64fbfaccd",
+            ),
+            (
+                "101112131415161718191a1b",
+                "000102030405060708090a0b0c0d0e0f10111213",
+                "202122232425262728292a2b2c2d2e2f3031323334353637",
+                "e3b201a9f5b71a7a9b1ceaeccd97e70b6176aad9a4428aa5",
+                "484392fbc1b09951",
+            ),
+        ];
+
+        for &(nonce, aad, pt, ct, tag) in vectors.iter() {
+            let nonce = hex::decode(nonce).unwrap();
+            let aad = hex::decode(aad).unwrap();
+            let pt = hex::decode(pt).unwrap();
+            let tag = hex::decode(tag).unwrap();
+
+            let (ciphertext, out_tag) =
+                CipherCtx::encrypt_ccm(Cipher::aes_128_ccm(), &key, &nonce, &aad, &pt, tag.len())
+                    .unwrap();
+            assert_eq!(hex::encode(&ciphertext), ct);
+            assert_eq!(out_tag, tag);
+
+            let plaintext = CipherCtx::decrypt_ccm(
+                Cipher::aes_128_ccm(),
+                &key,
+                &nonce,
+                &aad,
+                &ciphertext,
+                &tag,
+            )
+            .unwrap();
+            assert_eq!(plaintext, pt);
+
+            let mut tampered = tag.clone();
+            tampered[0] ^= 1;
+            assert!(CipherCtx::decrypt_ccm(
+                Cipher::aes_128_ccm(),
+                &key,
+                &nonce,
+                &aad,
+                &ciphertext,
+                &tampered
+            )
+            .is_err());
+        }
+
+        assert!(
+            CipherCtx::encrypt_ccm(Cipher::aes_128_ccm(), &key, &[0; 6], &[], b"data", 16).is_err()
+        );
+        assert!(
+            CipherCtx::encrypt_ccm(Cipher::aes_128_ccm(), &key, &[0; 12], &[], b"data", 5).is_err()
+        );
+        assert!(
+            CipherCtx::encrypt_ccm(Cipher::aes_128_gcm(), &key, &[0; 12], &[], b"data", 16)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn one_shot_aead() {
+        let ciphers = [
+            (Cipher::aes_128_gcm(), 16),
+            #[cfg(ossl110)]
+            (Cipher::chacha20_poly1305(), 32),
+        ];
+
+        for &(cipher, key_len) in ciphers.iter() {
+            let key = vec![0x42; key_len];
+            let iv = [0x24; 12];
+            let aad = b"header";
+            let plaintext = b"some plaintext to seal";
+
+            let mut tag = [0; 16];
+            let ciphertext =
+                CipherCtx::encrypt_aead(cipher, &key, Some(&iv), aad, plaintext, &mut tag).unwrap();
+            let sealed = CipherCtx::encrypt(cipher, &key, Some(&iv), plaintext).unwrap();
+            assert_eq!(&sealed[..plaintext.len()], &ciphertext[..]);
+
+            let decrypted =
+                CipherCtx::decrypt_aead(cipher, &key, Some(&iv), aad, &ciphertext, &tag).unwrap();
+            assert_eq!(decrypted, &plaintext[..]);
+
+            let mut tampered = ciphertext.clone();
+            tampered[3] ^= 0x80;
+            assert!(
+                CipherCtx::decrypt_aead(cipher, &key, Some(&iv), aad, &tampered, &tag).is_err()
+            );
+            assert!(
+                CipherCtx::decrypt_aead(cipher, &key, Some(&iv), b"other", &ciphertext, &tag)
+                    .is_err()
+            );
+        }
+
+        let mut tag = [0; 16];
+        assert!(CipherCtx::encrypt_aead(
+            Cipher::aes_128_cbc(),
+            &[0; 16],
+            Some(&[0; 16]),
+            &[],
+            b"data",
+            &mut tag
+        )
+        .is_err());
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn flags() {
+        let kek = [0x42; 16];
+        let key = [0x24; 32];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.set_flags(CipherCtxFlags::WRAP_ALLOW);
+        assert_eq!(
+            ctx.test_flags(CipherCtxFlags::all()),
+            CipherCtxFlags::WRAP_ALLOW
+        );
+        ctx.encrypt_init(Some(Cipher::aes_128_wrap()), Some(&kek), None)
+            .unwrap();
+        let mut wrapped = vec![];
+        ctx.cipher_update_vec(&key, &mut wrapped).unwrap();
+        ctx.cipher_final_vec(&mut wrapped).unwrap();
+        assert_eq!(wrapped.len(), key.len() + 8);
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.set_flags(CipherCtxFlags::WRAP_ALLOW);
+        ctx.decrypt_init(Some(Cipher::aes_128_wrap()), Some(&kek), None)
+            .unwrap();
+        let mut unwrapped = vec![];
+        ctx.cipher_update_vec(&wrapped, &mut unwrapped).unwrap();
+        ctx.cipher_final_vec(&mut unwrapped).unwrap();
+        assert_eq!(unwrapped, key);
+
+        ctx.set_flags(CipherCtxFlags::NO_PADDING);
+        ctx.clear_flags(CipherCtxFlags::WRAP_ALLOW);
+        assert_eq!(
+            ctx.test_flags(CipherCtxFlags::all()),
+            CipherCtxFlags::NO_PADDING
+        );
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn set_params() {
+        use crate::ossl_param::OsslParamBuilder;
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), None, None)
+            .unwrap();
+        assert_eq!(ctx.iv_length(), 12);
+
+        let mut builder = OsslParamBuilder::new().unwrap();
+        builder.add_size_t("ivlen", 8).unwrap();
+        let params = builder.to_param().unwrap();
+        ctx.set_params(&params).unwrap();
+        assert_eq!(ctx.iv_length(), 8);
+
+        let mut builder = OsslParamBuilder::new().unwrap();
+        builder.add_size_t("ivlen", 0).unwrap();
+        assert!(ctx.set_params(&builder.to_param().unwrap()).is_err());
+    }
+
+    #[test]
+    #[cfg(ossl110)]
+    fn key_wrap() {
+        // RFC 3394 sections 4.1 and 4.6
+        let kek = hex::decode("000102030405060708090A0B0C0D0E0F").unwrap();
+        let key = hex::decode("00112233445566778899AABBCCDDEEFF").unwrap();
+        let wrapped = CipherCtx::wrap_key(Cipher::aes_128_wrap(), &kek, None, &key).unwrap();
+        assert_eq!(
+            hex::encode(&wrapped),
+            "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5"
+        );
+        assert_eq!(
+            CipherCtx::unwrap_key(Cipher::aes_128_wrap(), &kek, None, &wrapped).unwrap(),
+            key
+        );
+
+        let kek = hex::decode("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F")
+            .unwrap();
+        let key = hex::decode("00112233445566778899AABBCCDDEEFF000102030405060708090A0B0C0D0E0F")
+            .unwrap();
+        let wrapped = CipherCtx::wrap_key(Cipher::aes_256_wrap(), &kek, None, &key).unwrap();
+        assert_eq!(
+            hex::encode(&wrapped),
+            "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21"
+        );
+        assert_eq!(
+            CipherCtx::unwrap_key(Cipher::aes_256_wrap(), &kek, None, &wrapped).unwrap(),
+            key
+        );
+
+        let mut tampered = wrapped.clone();
+        tampered[0] ^= 1;
+        assert!(CipherCtx::unwrap_key(Cipher::aes_256_wrap(), &kek, None, &tampered).is_err());
+        assert!(CipherCtx::wrap_key(Cipher::aes_256_cbc(), &kek, None, &key).is_err());
+
+        // a custom IV must match on both sides
+        let iv = [0x42; 8];
+        let wrapped = CipherCtx::wrap_key(Cipher::aes_256_wrap(), &kek, Some(&iv), &key).unwrap();
+        assert!(CipherCtx::unwrap_key(Cipher::aes_256_wrap(), &kek, None, &wrapped).is_err());
+        assert_eq!(
+            CipherCtx::unwrap_key(Cipher::aes_256_wrap(), &kek, Some(&iv), &wrapped).unwrap(),
+            key
+        );
+
+        // RFC 5649 section 6
+        let kek = hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+        let key = hex::decode("466f7250617369").unwrap();
+        let wrapped = CipherCtx::wrap_key(Cipher::aes_192_wrap_pad(), &kek, None, &key).unwrap();
+        assert_eq!(hex::encode(&wrapped), "afbeb0f07dfbf5419200f2ccb50bb24f");
+        assert_eq!(
+            CipherCtx::unwrap_key(Cipher::aes_192_wrap_pad(), &kek, None, &wrapped).unwrap(),
+            key
+        );
+
+        let key = hex::decode("c37b7e6492584340bed12207808941155068f738").unwrap();
+        let wrapped = CipherCtx::wrap_key(Cipher::aes_192_wrap_pad(), &kek, None, &key).unwrap();
+        assert_eq!(
+            hex::encode(&wrapped),
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a"
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let cipher = Cipher::aes_128_cbc();
+        let mut ctx = CipherCtx::new().unwrap();
+
+        ctx.encrypt_init(Some(cipher), Some(&[0x42; 16]), Some(&[0x24; 16]))
+            .unwrap();
+        let mut first = vec![];
+        ctx.cipher_update_vec(b"first message", &mut first).unwrap();
+        ctx.cipher_final_vec(&mut first).unwrap();
+
+        ctx.reset().unwrap();
+        assert!(
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| ctx.block_size())).is_err()
+        );
+
+        ctx.encrypt_init(Some(cipher), Some(&[0x01; 16]), Some(&[0x02; 16]))
+            .unwrap();
+        let mut second = vec![];
+        ctx.cipher_update_vec(b"a second, unrelated message", &mut second)
+            .unwrap();
+        ctx.cipher_final_vec(&mut second).unwrap();
+
+        let expected = |key: &[u8], iv: &[u8], data: &[u8]| {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(cipher), Some(key), Some(iv)).unwrap();
+            let mut out = vec![];
+            ctx.cipher_update_vec(data, &mut out).unwrap();
+            ctx.cipher_final_vec(&mut out).unwrap();
+            out
+        };
+        assert_eq!(first, expected(&[0x42; 16], &[0x24; 16], b"first message"));
+        assert_eq!(
+            second,
+            expected(&[0x01; 16], &[0x02; 16], b"a second, unrelated message")
+        );
+    }
+
+    #[test]
+    fn try_clone() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+        let data = [0x55; 40];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update(b"header", None).unwrap();
+        let mut first = vec![];
+        ctx.cipher_update_vec(&data[..19], &mut first).unwrap();
+
+        let mut clone = ctx.try_clone().unwrap();
+        let mut second = first.clone();
+        ctx.cipher_update_vec(&data[19..], &mut first).unwrap();
+        ctx.cipher_final_vec(&mut first).unwrap();
+        clone.cipher_update_vec(&data[19..], &mut second).unwrap();
+        clone.cipher_final_vec(&mut second).unwrap();
+        assert_eq!(first, second);
+
+        let mut tag = [0; 16];
+        ctx.tag(&mut tag).unwrap();
+        let mut clone_tag = [0; 16];
+        clone.tag(&mut clone_tag).unwrap();
+        assert_eq!(tag, clone_tag);
+
+        let mut expected = vec![];
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update(b"header", None).unwrap();
+        ctx.cipher_update_vec(&data, &mut expected).unwrap();
+        ctx.cipher_final_vec(&mut expected).unwrap();
+        assert_eq!(first, expected);
+    }
+
+    #[test]
+    fn nonce_tracker() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let other_key = [0x43; 16];
+        let tracker = Arc::new(Mutex::new(NonceTracker::new(2).unwrap()));
+
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.set_nonce_tracker(tracker.clone());
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&[1; 12]))
+            .unwrap();
+        ctx.encrypt_init(None, None, Some(&[2; 12])).unwrap();
+        assert!(ctx.encrypt_init(None, None, Some(&[2; 12])).is_err());
+        assert_eq!(tracker.lock().unwrap().len(), 2);
+
+        // the same nonce under a different key, or when decrypting, is fine
+        ctx.encrypt_init(None, Some(&other_key), Some(&[2; 12]))
+            .unwrap();
+        ctx.decrypt_init(None, Some(&key), Some(&[2; 12])).unwrap();
+
+        // a second context sharing the tracker detects reuse too
+        let mut ctx2 = CipherSession::new().unwrap();
+        ctx2.set_nonce_tracker(tracker.clone());
+        ctx2.encrypt_init(Some(cipher), Some(&other_key), None)
+            .unwrap();
+        assert!(ctx2.encrypt_init(None, None, Some(&[2; 12])).is_err());
+
+        // the oldest pair has dropped out of the window
+        ctx2.encrypt_init(None, Some(&key), Some(&[1; 12])).unwrap();
+        assert_eq!(tracker.lock().unwrap().len(), 2);
+
+        // keys set before attaching the tracker are unknown to it
+        let mut ctx3 = CipherSession::new().unwrap();
+        ctx3.encrypt_init(Some(cipher), Some(&key), None).unwrap();
+        ctx3.set_nonce_tracker(tracker);
+        assert!(ctx3.encrypt_init(None, None, Some(&[3; 12])).is_err());
+
+        // non-AEAD ciphers are not tracked
+        let mut ctx4 = CipherSession::new().unwrap();
+        ctx4.set_nonce_tracker(Arc::new(Mutex::new(NonceTracker::new(2).unwrap())));
+        ctx4.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&[1; 16]))
+            .unwrap();
+        ctx4.encrypt_init(None, None, Some(&[1; 16])).unwrap();
+    }
+
+    #[test]
+    fn nonce_tracker_poisoned() {
+        let tracker = Arc::new(Mutex::new(NonceTracker::new(2).unwrap()));
+        let poisoner = tracker.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poisoning the tracker");
+        })
+        .join();
+        assert!(tracker.is_poisoned());
+
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.set_nonce_tracker(tracker);
+        ctx.encrypt_init(
+            Some(Cipher::aes_128_gcm()),
+            Some(&[0x42; 16]),
+            Some(&[1; 12]),
+        )
+        .unwrap();
+        assert!(ctx.encrypt_init(None, None, Some(&[1; 12])).is_err());
+    }
+
+    #[test]
+    fn encrypt_init_checked() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let tracker = Mutex::new(NonceTracker::new(2).unwrap());
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init_checked(Some(cipher), &key, &[1; 12], &tracker)
+            .unwrap();
+        assert!(ctx
+            .encrypt_init_checked(None, &key, &[1; 12], &tracker)
+            .is_err());
+        ctx.encrypt_init_checked(None, &[0x43; 16], &[1; 12], &tracker)
+            .unwrap();
+        assert_eq!(tracker.lock().unwrap().len(), 2);
+
+        // a tracker shared with a session sees the pairs recorded by either
+        let tracker = Arc::new(Mutex::new(NonceTracker::new(2).unwrap()));
+        let mut session = CipherSession::new().unwrap();
+        session.set_nonce_tracker(tracker.clone());
+        session
+            .encrypt_init(Some(cipher), Some(&key), Some(&[2; 12]))
+            .unwrap();
+        assert!(ctx
+            .encrypt_init_checked(None, &key, &[2; 12], &tracker)
+            .is_err());
+
+        // non-AEAD ciphers are not tracked
+        let tracker = Mutex::new(NonceTracker::new(2).unwrap());
+        ctx.encrypt_init_checked(Some(Cipher::aes_128_cbc()), &key, &[1; 16], &tracker)
+            .unwrap();
+        assert!(tracker.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "nonce has already been used")]
+    fn nonce_reuse_check() {
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.enable_nonce_reuse_check().unwrap();
+        ctx.encrypt_init(
+            Some(Cipher::aes_128_gcm()),
+            Some(&[0x42; 16]),
+            Some(&[1; 12]),
+        )
+        .unwrap();
+
+        let mut ctx2 = ctx.try_clone().unwrap();
+        ctx2.encrypt_init(None, None, Some(&[1; 12])).unwrap();
+    }
+
+    #[test]
+    fn nonce_reuse_check_fresh_nonces() {
+        let key = [0x42; 16];
+
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.enable_nonce_reuse_check().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&key), Some(&[1; 12]))
+            .unwrap();
+        ctx.encrypt_init(None, None, Some(&[2; 12])).unwrap();
+        ctx.encrypt_init(None, Some(&[0x43; 16]), Some(&[1; 12]))
+            .unwrap();
+        ctx.decrypt_init(None, Some(&key), Some(&[1; 12])).unwrap();
+    }
+
+    #[test]
+    fn usage_limit() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.set_usage_limit(100);
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut out = vec![];
+        ctx.set_aad(b"aad is not counted").unwrap();
+        ctx.cipher_update_vec(&[0; 60], &mut out).unwrap();
+        ctx.cipher_update_vec(&[0; 40], &mut out).unwrap();
+        assert!(ctx.cipher_update_vec(&[0; 1], &mut out).is_err());
+
+        ctx.encrypt_init(None, None, Some(&iv)).unwrap();
+        assert!(ctx.cipher_update_vec(&[0; 1], &mut out).is_err());
+
+        ctx.encrypt_init(None, Some(&key), Some(&iv)).unwrap();
+        ctx.cipher_update_vec(&[0; 100], &mut out).unwrap();
+
+        ctx.decrypt_init(None, Some(&key), Some(&iv)).unwrap();
+        ctx.cipher_update_vec(&[0; 200], &mut out).unwrap();
+    }
+
+    #[test]
+    fn derive_nonce() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_gcm()), Some(&[0; 16]), None)
+            .unwrap();
+
+        let nonce = ctx.derive_nonce(b"nonce key", b"message").unwrap();
+        assert_eq!(nonce.len(), 12);
+        assert_eq!(nonce, ctx.derive_nonce(b"nonce key", b"message").unwrap());
+        assert_ne!(nonce, ctx.derive_nonce(b"nonce key", b"other").unwrap());
+        assert_ne!(nonce, ctx.derive_nonce(b"other key", b"message").unwrap());
+
+        ctx.encrypt_init(None, None, Some(&nonce)).unwrap();
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn seal_dual() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let nonce = [0x24; 12];
+        let mut body_nonce = nonce;
+        body_nonce[11] += 1;
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&nonce))
+            .unwrap();
+        let (ciphertext, header_tag, body_tag) = ctx.seal_dual(b"header", b"body").unwrap();
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&nonce))
+            .unwrap();
+        ctx.set_tag(&header_tag).unwrap();
+        ctx.cipher_update(b"header", None).unwrap();
+        ctx.cipher_final_vec(&mut vec![]).unwrap();
+
+        ctx.decrypt_init(None, None, Some(&body_nonce)).unwrap();
+        ctx.set_tag(&body_tag).unwrap();
+        let mut body = vec![];
+        ctx.cipher_update_vec(&ciphertext, &mut body).unwrap();
+        ctx.cipher_final_vec(&mut body).unwrap();
+        assert_eq!(body, b"body");
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&nonce))
+            .unwrap();
+        ctx.set_tag(&header_tag).unwrap();
+        ctx.cipher_update(b"other", None).unwrap();
+        assert!(ctx.cipher_final_vec(&mut vec![]).is_err());
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&[0xff; 12]))
+            .unwrap();
+        assert!(ctx.seal_dual(b"header", b"body").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "context is not initialized for encryption")]
+    #[cfg(ossl300)]
+    fn seal_dual_decrypting() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(Cipher::aes_128_gcm()), Some(&[0; 16]), Some(&[0; 12]))
+            .unwrap();
+        let _ = ctx.seal_dual(b"header", b"body");
+    }
+
+    #[test]
+    #[should_panic(expected = "cipher is not an authenticated cipher")]
+    #[cfg(ossl300)]
+    fn seal_dual_non_aead() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        let _ = ctx.seal_dual(b"header", b"body");
+    }
+
+    #[test]
+    #[should_panic(expected = "tag length is not 16 bytes")]
+    #[cfg(ossl300)]
+    fn seal_dual_ccm() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ccm()), Some(&[0; 16]), Some(&[0; 12]))
+            .unwrap();
+        let _ = ctx.seal_dual(b"header", b"body");
+    }
+
+    #[test]
+    #[cfg(ossl300)]
+    fn checkpoint_restore() {
+        let key = [0x42; 16];
+        let iv = [0xff; 16];
+        let plaintext = (0..100).collect::<Vec<u8>>();
+
+        for cipher in &[
+            Cipher::aes_128_cfb128(),
+            Cipher::aes_128_ofb(),
+            Cipher::aes_128_ctr(),
+        ] {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            let mut expected = vec![];
+            ctx.cipher_update_vec(&plaintext, &mut expected).unwrap();
+
+            for &split in &[0, 16, 37] {
+                let mut ctx = CipherCtx::new().unwrap();
+                ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+                    .unwrap();
+                let mut ciphertext = vec![];
+                ctx.cipher_update_vec(&plaintext[..split], &mut ciphertext)
+                    .unwrap();
+                let checkpoint = ctx.checkpoint().unwrap().to_bytes();
+                drop(ctx);
+
+                let checkpoint = CipherCheckpoint::from_bytes(&checkpoint).unwrap();
+                let mut ctx = CipherCtx::restore(&checkpoint, &key).unwrap();
+                ctx.cipher_update_vec(&plaintext[split..], &mut ciphertext)
+                    .unwrap();
+                assert_eq!(ciphertext, expected);
+
+                let mut ctx = CipherCtx::new().unwrap();
+                ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+                    .unwrap();
+                let mut decrypted = vec![];
+                ctx.cipher_update_vec(&expected[..split], &mut decrypted)
+                    .unwrap();
+                let checkpoint = ctx.checkpoint().unwrap();
+                let mut ctx = CipherCtx::restore(&checkpoint, &key).unwrap();
+                ctx.cipher_update_vec(&expected[split..], &mut decrypted)
+                    .unwrap();
+                assert_eq!(decrypted, plaintext);
+            }
+        }
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        assert!(ctx.checkpoint().is_err());
+        assert!(CipherCheckpoint::from_bytes(&[1, 2, 3]).is_err());
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update_vec(&plaintext[..5], &mut vec![]).unwrap();
+        let valid = ctx.checkpoint().unwrap().to_bytes();
+        assert!(CipherCheckpoint::from_bytes(&valid).is_ok());
+
+        let mut bad_num = valid.clone();
+        bad_num[6] = 255;
+        assert!(CipherCheckpoint::from_bytes(&bad_num).is_err());
+        bad_num[6] = 16;
+        assert!(CipherCheckpoint::from_bytes(&bad_num).is_err());
+
+        let mut short_iv = valid[..8 + 4].to_vec();
+        short_iv[7] = 4;
+        assert!(CipherCheckpoint::from_bytes(&short_iv).is_err());
+
+        let mut cbc = valid;
+        cbc[1..5].copy_from_slice(&Nid::AES_128_CBC.as_raw().to_be_bytes());
+        assert!(CipherCheckpoint::from_bytes(&cbc).is_err());
+    }
+
+    #[test]
+    fn open_with_expected() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let (ciphertext, tag) = ctx
+            .authenticate(b"header")
+            .unwrap()
+            .encrypt(b"MAGIC1 payload")
+            .unwrap();
+
+        let open = |aad: &[u8], tag: &[u8], prefix: &[u8]| {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            ctx.open_with_expected(aad, &ciphertext, tag, prefix)
+        };
+
+        assert_eq!(open(b"header", &tag, b"MAGIC1").unwrap(), b"MAGIC1 payload");
+        assert!(open(b"header", &tag, b"MAGIC2").is_err());
+        assert!(open(b"header", &tag, b"MAGIC1 payload and more").is_err());
+        assert!(open(b"other", &tag, b"MAGIC1").is_err());
+    }
+
+    #[test]
+    fn seal_open_aead() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        let mut tag = [0; 16];
+        let ciphertext = ctx.seal(b"header", b"payload", &mut tag).unwrap();
+
+        let mut expected_tag = [0; 16];
+        let expected = CipherCtx::encrypt_aead(
+            cipher,
+            &key,
+            Some(&iv),
+            b"header",
+            b"payload",
+            &mut expected_tag,
+        )
+        .unwrap();
+        assert_eq!(ciphertext, expected);
+        assert_eq!(tag, expected_tag);
+
+        let open = |aad: &[u8], tag: &[u8]| {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+                .unwrap();
+            ctx.open(aad, &ciphertext, tag)
+        };
+
+        assert_eq!(open(b"header", &tag).unwrap(), b"payload");
+        assert!(open(b"other", &tag).is_err());
+        let mut bad_tag = tag;
+        bad_tag[0] ^= 1;
+        assert!(open(b"header", &bad_tag).is_err());
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.decrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        assert!(ctx.seal(b"header", b"payload", &mut tag).is_err());
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&[0; 16]))
+            .unwrap();
+        assert!(ctx.seal(b"", b"payload", &mut tag).is_err());
+    }
+
+    #[test]
+    fn buffered_len() {
+        let mut ctx = CipherSession::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        assert_eq!(ctx.buffered_len(), 0);
+
+        let mut output = [0; 64];
+        assert_eq!(ctx.cipher_update(&[0; 10], &mut output).unwrap(), 0);
+        assert_eq!(ctx.buffered_len(), 10);
+        assert_eq!(ctx.cipher_update(&[0; 10], &mut output).unwrap(), 16);
+        assert_eq!(ctx.buffered_len(), 4);
+        ctx.cipher_final(&mut output).unwrap();
+        assert_eq!(ctx.buffered_len(), 0);
+
+        // The last complete block is held back when decrypting with padding.
+        ctx.decrypt_init(None, Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        assert_eq!(ctx.cipher_update(&[0; 32], &mut output).unwrap(), 16);
+        assert_eq!(ctx.buffered_len(), 16);
+
+        ctx.decrypt_init(None, None, Some(&[0; 16])).unwrap();
+        ctx.ctx_mut().set_padding(false);
+        assert_eq!(ctx.cipher_update(&[0; 32], &mut output).unwrap(), 32);
+        assert_eq!(ctx.buffered_len(), 0);
+
+        ctx.encrypt_init(None, Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        ctx.cipher_update(&[0; 10], &mut output).unwrap();
+        assert_eq!(ctx.buffered_len(), 10);
+        ctx.reset().unwrap();
+        assert_eq!(ctx.buffered_len(), 0);
+    }
+
+    #[test]
+    fn try_update_output_too_small() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+
+        let mut output = [0; 47];
+        match ctx.try_cipher_update(&[0; 32], Some(&mut output)) {
+            Err(CipherError::OutputTooSmall { needed, got }) => {
+                assert_eq!(needed, 48);
+                assert_eq!(got, 47);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let mut output = [0; 48];
+        assert_eq!(
+            ctx.try_cipher_update(&[0; 32], Some(&mut output)).unwrap(),
+            32
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "output buffer too small")]
+    fn update_output_too_small() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        let _ = ctx.cipher_update(&[0; 32], Some(&mut [0; 47]));
+    }
+
+    #[test]
+    fn update_to_writer() {
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+        let data = (0..10 * 1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut ciphertext = vec![];
+        let mut len = ctx
+            .cipher_update_to_writer(&data[..12345], &mut ciphertext)
+            .unwrap();
+        len += ctx
+            .cipher_update_to_writer(&data[12345..], &mut ciphertext)
+            .unwrap();
+        len += ctx.cipher_final_to_writer(&mut ciphertext).unwrap();
+        assert_eq!(len, ciphertext.len());
+        assert_eq!(ciphertext.len(), data.len() + 16);
+
+        ctx.decrypt_init(None, Some(&key), Some(&iv)).unwrap();
+        let mut plaintext = vec![];
+        ctx.cipher_update_vec(&ciphertext, &mut plaintext).unwrap();
+        ctx.cipher_final_vec(&mut plaintext).unwrap();
+        assert!(plaintext == data);
+    }
+
+    #[test]
+    fn update_vectored() {
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+        let data = (0..100).map(|i| i as u8).collect::<Vec<_>>();
+
+        let encrypt = |bufs: &[&[u8]]| {
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+                .unwrap();
+            let mut ciphertext = vec![];
+            let len = ctx.cipher_update_vectored(bufs, &mut ciphertext).unwrap();
+            assert_eq!(len, ciphertext.len());
+            ctx.cipher_final_vec(&mut ciphertext).unwrap();
+            ciphertext
+        };
+
+        let expected = encrypt(&[&data]);
+        assert_eq!(encrypt(&[&data[..7], &data[7..40], &data[40..]]), expected);
+        assert_eq!(encrypt(&[&data[..16], &[], &data[16..]]), expected);
+    }
+
+    #[test]
+    fn cipher_writer() {
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+        let data = (0..100_000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut writer = CipherWriter::new(&mut ctx, vec![]);
+        // Odd-sized writes leave partial blocks buffered in the context in between.
+        for chunk in data.chunks(1000 - 7) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.flush().unwrap();
+        let ciphertext = writer.finish().unwrap();
+        assert_eq!(ciphertext.len(), (data.len() / 16 + 1) * 16);
+
+        ctx.decrypt_init(None, Some(&key), Some(&iv)).unwrap();
+        let mut writer = CipherWriter::new(&mut ctx, vec![]);
+        io::copy(&mut &ciphertext[..], &mut writer).unwrap();
+        let plaintext = writer.finish().unwrap();
+        assert!(plaintext == data);
+    }
+
+    #[test]
+    fn update_inplace() {
+        let key = [0x42; 16];
+        let iv = [0x24; 16];
+        let plaintext = b"Lorem ipsum dolor sit amet";
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&key), Some(&iv))
+            .unwrap();
+        let mut expected = vec![];
+        ctx.cipher_update_vec(plaintext, &mut expected).unwrap();
+
+        let mut data = plaintext.to_vec();
+        data.extend_from_slice(b"trailer");
+        ctx.encrypt_init(None, None, Some(&iv)).unwrap();
+        ctx.cipher_update_inplace(&mut data, plaintext.len())
+            .unwrap();
+        assert_eq!(&data[..plaintext.len()], &expected[..]);
+        assert_eq!(&data[plaintext.len()..], b"trailer");
+
+        ctx.decrypt_init(None, None, Some(&iv)).unwrap();
+        ctx.cipher_update_inplace(&mut data, plaintext.len())
+            .unwrap();
+        assert_eq!(&data[..plaintext.len()], &plaintext[..]);
+    }
+
+    #[test]
+    #[should_panic(expected = "block size of 1")]
+    fn update_inplace_block_cipher() {
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_cbc()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        let _ = ctx.cipher_update_inplace(&mut [0; 32], 16);
+    }
+
+    #[test]
+    fn xts_sector() {
+        // IEEE 1619 XTS-AES-128 vectors 2, 3 and 15. The data unit numbers are listed there as little-endian bytes.
+        let vectors: [(&str, u64, &str, &str); 3] = [
+            (
+                "1111111111111111111111111111111122222222222222222222222222222222",
+                0x33_3333_3333,
+                "4444444444444444444444444444444444444444444444444444444444444444",
+                "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f022222222222222222222222222222222",
+                0x33_3333_3333,
+                "4444444444444444444444444444444444444444444444444444444444444444",
+                "af85336b597afc1a900b2eb21ec949d292df4c047e0b21532186a5971a227a89",
+            ),
+            (
+                "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+                0x12_3456_789a,
+                "000102030405060708090a0b0c0d0e0f10",
+                "6c1625db4671522d3d7599601de7ca09ed",
+            ),
+        ];
+
+        for (key, sector, pt, ct) in vectors.iter() {
+            let key = hex::decode(key).unwrap();
+            let pt = hex::decode(pt).unwrap();
+
+            let mut ctx = CipherCtx::new().unwrap();
+            ctx.encrypt_init(Some(Cipher::aes_128_xts()), Some(&key), None)
+                .unwrap();
+            let mut data = pt.clone();
+            ctx.encrypt_sector(*sector, &mut data).unwrap();
+            assert_eq!(hex::encode(&data), *ct);
+
+            // The context is reusable, and the tweak depends on the data unit number.
+            let mut other = pt.clone();
+            ctx.encrypt_sector(sector + 1, &mut other).unwrap();
+            assert_ne!(other, data);
+            assert!(ctx.decrypt_sector(*sector, &mut data).is_err());
+
+            ctx.decrypt_init(Some(Cipher::aes_128_xts()), Some(&key), None)
+                .unwrap();
+            ctx.decrypt_sector(*sector, &mut data).unwrap();
+            assert_eq!(data, pt);
+        }
+
+        let mut ctx = CipherCtx::new().unwrap();
+        let key = hex::decode(vectors[0].0).unwrap();
+        ctx.encrypt_init(Some(Cipher::aes_128_xts()), Some(&key), None)
+            .unwrap();
+        assert!(ctx.encrypt_sector(0, &mut [0; 15]).is_err());
+
+        ctx.encrypt_init(Some(Cipher::aes_128_ctr()), Some(&[0; 16]), Some(&[0; 16]))
+            .unwrap();
+        assert!(ctx.encrypt_sector(0, &mut [0; 16]).is_err());
+    }
+
+    #[test]
+    fn open_stream() {
+        let cipher = Cipher::aes_128_gcm();
+        let key = [0x42; 16];
+        let iv = [0x24; 12];
+        let aad = b"header";
+        let pt = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit";
+
+        let mut ctx = CipherCtx::new().unwrap();
+        ctx.encrypt_init(Some(cipher), Some(&key), Some(&iv))
+            .unwrap();
+        ctx.cipher_update(aad, None).unwrap();
+        let mut ct = vec![];
+        ctx.cipher_update_vec(pt, &mut ct).unwrap();
+        ctx.cipher_final_vec(&mut ct).unwrap();
+        let mut tag = [0; 16];
+        ctx.tag(&mut tag).unwrap();
+
+        let mut stream = OpenStream::new(cipher, &key, &iv).unwrap();
+        stream.aad_update(aad).unwrap();
+        for chunk in ct.chunks(7) {
+            stream.update(chunk).unwrap();
+        }
+        assert_eq!(stream.finalize(&tag).unwrap(), &pt[..]);
+
+        tag[0] ^= 1;
+        let mut stream = OpenStream::new(cipher, &key, &iv).unwrap();
+        stream.aad_update(aad).unwrap();
+        stream.update(&ct).unwrap();
+        assert!(stream.finalize(&tag).is_err());
     }
 }
@@ -17,13 +17,17 @@
 //! ```
 use cfg_if::cfg_if;
 use libc::{c_char, c_int, c_ulong};
+use once_cell::sync::Lazy;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::error;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::fmt;
 use std::io;
+use std::panic::Location;
 use std::ptr;
 use std::str;
+use std::sync::{Mutex, PoisonError};
 
 /// Collection of [`Error`]s from OpenSSL.
 ///
@@ -47,6 +51,37 @@ impl ErrorStack {
             error.put();
         }
     }
+
+    /// Raises an error detected by this crate rather than by OpenSSL, and returns the contents of the error stack.
+    ///
+    /// The error is put onto the OpenSSL error stack as coming from the `ERR_LIB_USER` library, with the location of
+    /// the caller and with `reason` as its data.
+    #[track_caller]
+    pub(crate) fn internal_error(reason: &str) -> ErrorStack {
+        let location = Location::caller();
+        ffi::init();
+        Error {
+            code: ffi::ERR_PACK(ffi::ERR_LIB_USER, 0, 0),
+            file: static_file_name(location.file()),
+            line: location.line() as c_int,
+            func: ptr::null(),
+            data: Some(Cow::Owned(reason.to_string())),
+        }
+        .put();
+        ErrorStack::get()
+    }
+}
+
+// Returns a NUL-terminated copy of `file` which is never freed, as the OpenSSL error stack only keeps a pointer to
+// the file name. One copy is made per file.
+fn static_file_name(file: &'static str) -> *const c_char {
+    static FILES: Lazy<Mutex<HashMap<&'static str, &'static CStr>>> = Lazy::new(Default::default);
+
+    let mut files = FILES.lock().unwrap_or_else(PoisonError::into_inner);
+    files
+        .entry(file)
+        .or_insert_with(|| Box::leak(CString::new(file).unwrap().into_boxed_c_str()))
+        .as_ptr()
 }
 
 impl ErrorStack {
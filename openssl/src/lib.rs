@@ -159,6 +159,8 @@ pub mod memcmp;
 pub mod nid;
 #[cfg(not(osslconf = "OPENSSL_NO_OCSP"))]
 pub mod ocsp;
+#[cfg(ossl300)]
+pub mod ossl_param;
 pub mod pkcs12;
 pub mod pkcs5;
 pub mod pkcs7;
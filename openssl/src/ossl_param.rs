@@ -0,0 +1,171 @@
+//! Parameter arrays for provider-based algorithms.
+//!
+//! Under OpenSSL 3.0, many algorithms are configured through arrays of `OSSL_PARAM` values identified by name,
+//! rather than through algorithm-specific control functions. An [`OsslParamArray`] is built with an
+//! [`OsslParamBuilder`], and can then be passed to methods such as
+//! [`CipherCtxRef::set_params`](crate::cipher_ctx::CipherCtxRef::set_params).
+//!
+//! Requires OpenSSL 3.0.0 or newer.
+//!
+//! # Examples
+//!
+//! ```
+//! use openssl::ossl_param::OsslParamBuilder;
+//!
+//! let mut builder = OsslParamBuilder::new().unwrap();
+//! builder.add_size_t("ivlen", 8).unwrap();
+//! let params = builder.to_param().unwrap();
+//! ```
+use crate::error::ErrorStack;
+use crate::{cvt, cvt_p};
+use libc::{c_char, c_void};
+use openssl_macros::corresponds;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem;
+
+/// A builder for an [`OsslParamArray`].
+///
+/// String and octet string values are borrowed until the array is built, as OpenSSL only copies them at that
+/// point.
+pub struct OsslParamBuilder<'a> {
+    bld: *mut ffi::OSSL_PARAM_BLD,
+    // OpenSSL refers to the keys rather than copying them, so they are kept alive alongside the parameters.
+    keys: Vec<CString>,
+    _p: PhantomData<&'a [u8]>,
+}
+
+impl<'a> OsslParamBuilder<'a> {
+    /// Creates a new, empty builder.
+    #[corresponds(OSSL_PARAM_BLD_new)]
+    pub fn new() -> Result<OsslParamBuilder<'a>, ErrorStack> {
+        ffi::init();
+
+        unsafe {
+            let bld = cvt_p(ffi::OSSL_PARAM_BLD_new())?;
+            Ok(OsslParamBuilder {
+                bld,
+                keys: vec![],
+                _p: PhantomData,
+            })
+        }
+    }
+
+    /// Adds a signed integer parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` contains a NUL byte.
+    #[corresponds(OSSL_PARAM_BLD_push_int)]
+    pub fn add_int(&mut self, key: &str, value: i32) -> Result<(), ErrorStack> {
+        let key = self.key(key);
+        unsafe { cvt(ffi::OSSL_PARAM_BLD_push_int(self.bld, key, value)).map(|_| ()) }
+    }
+
+    /// Adds an unsigned integer parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` contains a NUL byte.
+    #[corresponds(OSSL_PARAM_BLD_push_uint)]
+    pub fn add_uint(&mut self, key: &str, value: u32) -> Result<(), ErrorStack> {
+        let key = self.key(key);
+        unsafe { cvt(ffi::OSSL_PARAM_BLD_push_uint(self.bld, key, value)).map(|_| ()) }
+    }
+
+    /// Adds a `size_t` parameter, as used for lengths such as `ivlen`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` contains a NUL byte.
+    #[corresponds(OSSL_PARAM_BLD_push_size_t)]
+    pub fn add_size_t(&mut self, key: &str, value: usize) -> Result<(), ErrorStack> {
+        let key = self.key(key);
+        unsafe { cvt(ffi::OSSL_PARAM_BLD_push_size_t(self.bld, key, value)).map(|_| ()) }
+    }
+
+    /// Adds a UTF-8 string parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` contains a NUL byte.
+    #[corresponds(OSSL_PARAM_BLD_push_utf8_string)]
+    pub fn add_utf8_string(&mut self, key: &str, value: &'a str) -> Result<(), ErrorStack> {
+        let key = self.key(key);
+        unsafe {
+            cvt(ffi::OSSL_PARAM_BLD_push_utf8_string(
+                self.bld,
+                key,
+                value.as_ptr() as *const c_char,
+                value.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Adds an octet string parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` contains a NUL byte.
+    #[corresponds(OSSL_PARAM_BLD_push_octet_string)]
+    pub fn add_octet_string(&mut self, key: &str, value: &'a [u8]) -> Result<(), ErrorStack> {
+        let key = self.key(key);
+        unsafe {
+            cvt(ffi::OSSL_PARAM_BLD_push_octet_string(
+                self.bld,
+                key,
+                value.as_ptr() as *const c_void,
+                value.len(),
+            ))
+            .map(|_| ())
+        }
+    }
+
+    /// Builds the parameter array.
+    #[corresponds(OSSL_PARAM_BLD_to_param)]
+    pub fn to_param(mut self) -> Result<OsslParamArray, ErrorStack> {
+        unsafe {
+            let params = cvt_p(ffi::OSSL_PARAM_BLD_to_param(self.bld))?;
+            Ok(OsslParamArray {
+                params,
+                _keys: mem::take(&mut self.keys),
+            })
+        }
+    }
+
+    fn key(&mut self, key: &str) -> *const c_char {
+        let key = CString::new(key).unwrap();
+        // The heap allocation of the string does not move when it is pushed.
+        let ptr = key.as_ptr();
+        self.keys.push(key);
+        ptr
+    }
+}
+
+impl Drop for OsslParamBuilder<'_> {
+    fn drop(&mut self) {
+        unsafe { ffi::OSSL_PARAM_BLD_free(self.bld) }
+    }
+}
+
+/// An array of `OSSL_PARAM` values, built with an [`OsslParamBuilder`].
+pub struct OsslParamArray {
+    params: *mut ffi::OSSL_PARAM,
+    _keys: Vec<CString>,
+}
+
+unsafe impl Send for OsslParamArray {}
+unsafe impl Sync for OsslParamArray {}
+
+impl OsslParamArray {
+    pub(crate) fn as_ptr(&self) -> *const ffi::OSSL_PARAM {
+        self.params
+    }
+}
+
+impl Drop for OsslParamArray {
+    fn drop(&mut self) {
+        unsafe { ffi::OSSL_PARAM_free(self.params) }
+    }
+}
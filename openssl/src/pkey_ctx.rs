@@ -62,7 +62,9 @@
 use crate::cipher::CipherRef;
 use crate::error::ErrorStack;
 use crate::md::MdRef;
-use crate::pkey::{HasPrivate, HasPublic, Id, PKey, PKeyRef, Private};
+#[cfg(ossl300)]
+use crate::ossl_param::OsslParamArray;
+use crate::pkey::{HasPrivate, HasPublic, Id, PKey, PKeyRef, Params, Private};
 use crate::rsa::Padding;
 use crate::{cvt, cvt_n, cvt_p};
 use foreign_types::{ForeignType, ForeignTypeRef};
@@ -318,6 +320,67 @@ impl<T> PkeyCtxRef<T> {
         Ok(())
     }
 
+    /// Prepares the context for parameter generation.
+    #[corresponds(EVP_PKEY_paramgen_init)]
+    #[inline]
+    pub fn paramgen_init(&mut self) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::EVP_PKEY_paramgen_init(self.as_ptr()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the length of the prime `p` of generated DSA parameters in bits.
+    ///
+    /// This is only useful for DSA keys.
+    #[corresponds(EVP_PKEY_CTX_set_dsa_paramgen_bits)]
+    #[inline]
+    pub fn set_dsa_paramgen_bits(&mut self, bits: u32) -> Result<(), ErrorStack> {
+        let bits = c_int::try_from(bits).unwrap();
+
+        unsafe {
+            cvt(ffi::EVP_PKEY_CTX_set_dsa_paramgen_bits(self.as_ptr(), bits))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the length of the sub-prime `q` of generated DSA parameters in bits.
+    ///
+    /// This is only useful for DSA keys.
+    #[corresponds(EVP_PKEY_CTX_set_dsa_paramgen_q_bits)]
+    #[inline]
+    pub fn set_dsa_paramgen_q_bits(&mut self, bits: u32) -> Result<(), ErrorStack> {
+        let bits = c_int::try_from(bits).unwrap();
+
+        unsafe {
+            cvt(ffi::EVP_PKEY_CTX_set_dsa_paramgen_q_bits(
+                self.as_ptr(),
+                bits,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets parameters of the algorithm by name.
+    ///
+    /// This allows configuring options for which there is no dedicated method, such as the `seed` used to generate
+    /// DSA parameters.
+    ///
+    /// Requires OpenSSL 3.0.0 or newer.
+    #[corresponds(EVP_PKEY_CTX_set_params)]
+    #[cfg(ossl300)]
+    #[inline]
+    pub fn set_params(&mut self, params: &OsslParamArray) -> Result<(), ErrorStack> {
+        unsafe {
+            cvt(ffi::EVP_PKEY_CTX_set_params(self.as_ptr(), params.as_ptr()))?;
+        }
+
+        Ok(())
+    }
+
     /// Prepares the context for key generation.
     #[corresponds(EVP_PKEY_keygen_init)]
     #[inline]
@@ -582,6 +645,17 @@ impl<T> PkeyCtxRef<T> {
             Ok(PKey::from_ptr(key))
         }
     }
+
+    /// Generates new parameters.
+    #[corresponds(EVP_PKEY_paramgen)]
+    #[inline]
+    pub fn paramgen(&mut self) -> Result<PKey<Params>, ErrorStack> {
+        unsafe {
+            let mut key = ptr::null_mut();
+            cvt(ffi::EVP_PKEY_paramgen(self.as_ptr(), &mut key))?;
+            Ok(PKey::from_ptr(key))
+        }
+    }
 }
 
 #[cfg(test)]
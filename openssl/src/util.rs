@@ -1,3 +1,5 @@
+#[cfg(ossl110)]
+use crate::cvt_p;
 use crate::error::ErrorStack;
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::{c_char, c_int, c_void};
@@ -66,6 +68,60 @@ where
     }
 }
 
+/// A `BN_GENCB` forwarding prime generation progress to the callback of a `CallbackState`.
+///
+/// The callback is passed the two event arguments of `BN_GENCB_call`, and aborts generation by returning `false`.
+/// The `CallbackState` must outlive this value.
+#[cfg(ossl110)]
+pub struct GenCb(*mut ffi::BN_GENCB);
+
+#[cfg(ossl110)]
+impl GenCb {
+    pub fn new<F>(cb_state: &mut CallbackState<F>) -> Result<GenCb, ErrorStack>
+    where
+        F: FnMut(c_int, c_int) -> bool,
+    {
+        unsafe {
+            let cb = cvt_p(ffi::BN_GENCB_new())?;
+            ffi::BN_GENCB_set(
+                cb,
+                Some(invoke_gen_cb::<F>),
+                cb_state as *mut CallbackState<F> as *mut c_void,
+            );
+            Ok(GenCb(cb))
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut ffi::BN_GENCB {
+        self.0
+    }
+}
+
+#[cfg(ossl110)]
+impl Drop for GenCb {
+    fn drop(&mut self) {
+        unsafe { ffi::BN_GENCB_free(self.0) }
+    }
+}
+
+#[cfg(ossl110)]
+unsafe extern "C" fn invoke_gen_cb<F>(a: c_int, b: c_int, cb: *mut ffi::BN_GENCB) -> c_int
+where
+    F: FnMut(c_int, c_int) -> bool,
+{
+    let callback = &mut *(ffi::BN_GENCB_get_arg(cb) as *mut CallbackState<F>);
+    let f = callback.cb.as_mut().unwrap();
+
+    match panic::catch_unwind(AssertUnwindSafe(|| f(a, b))) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(err) => {
+            callback.panic = Some(err);
+            0
+        }
+    }
+}
+
 pub trait ForeignTypeExt: ForeignType {
     unsafe fn from_ptr_opt(ptr: *mut Self::CType) -> Option<Self> {
         if ptr.is_null() {
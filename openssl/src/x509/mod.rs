@@ -1445,6 +1445,16 @@ foreign_type_and_impl_send_sync! {
     pub struct X509AlgorithmRef;
 }
 
+impl X509Algorithm {
+    from_der! {
+        /// Deserializes a DER-encoded AlgorithmIdentifier structure.
+        #[corresponds(d2i_X509_ALGOR)]
+        from_der,
+        X509Algorithm,
+        ffi::d2i_X509_ALGOR
+    }
+}
+
 impl X509AlgorithmRef {
     /// Returns the ASN.1 OID of this algorithm.
     pub fn object(&self) -> &Asn1ObjectRef {
@@ -68,6 +68,11 @@ fn main() {
         if version >= 0x010100000 {
             cfg.header("openssl/kdf.h");
         }
+        if version >= 0x030000000 {
+            cfg.header("openssl/provider.h");
+            cfg.header("openssl/params.h");
+            cfg.header("openssl/param_build.h");
+        }
     }
 
     #[allow(clippy::if_same_then_else)]